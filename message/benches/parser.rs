@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use esm_message::{parser::Parser, Data};
+
+mod baseline;
+
+// Builds a SqfResult similar to what esm_arma sends back, where the result is a stringified SQF array
+fn sqf_result(entries: usize) -> String {
+    let entry = r#"[[""key_1"",""value with """"quotes"""" and, commas""],[""key_2"",12345.6],[""key_3"",[true,false,nil,any,<null>]]]"#;
+
+    let result = vec![entry; entries].join(",");
+    format!(r#"[["type","sqf_result"],["content",[["result","[{result}]"]]]]"#)
+}
+
+// The same data, but as nested arrays instead of a string.
+// The baseline only reads nil types at the end of an array, so there is just the one
+fn nested_arrays(entries: usize) -> String {
+    let entry = r#"[["key_1","value with ""quotes"" and, commas"],["key_2",12345.6],["key_3",[true,false,nil]]]"#;
+
+    let result = vec![entry; entries].join(",");
+    format!(r#"[["type","test"],["content",[{result}]]]"#)
+}
+
+fn parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_arma");
+    group.sample_size(20);
+
+    for entries in [1_000, 10_000, 50_000] {
+        let input = sqf_result(entries);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("sqf_result", input.len()),
+            &input,
+            |b, input| b.iter(|| Parser::from_arma::<Data>(input).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("sqf_result_baseline", input.len()),
+            &input,
+            |b, input| b.iter(|| baseline::Parser::from_arma::<Data>(input).unwrap()),
+        );
+
+        let input = nested_arrays(entries);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("nested_arrays", input.len()),
+            &input,
+            |b, input| b.iter(|| Parser::from_arma::<serde_json::Value>(input).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("nested_arrays_baseline", input.len()),
+            &input,
+            |b, input| b.iter(|| baseline::Parser::from_arma::<serde_json::Value>(input).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, parser);
+criterion_main!(benches);
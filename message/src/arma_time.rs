@@ -0,0 +1,217 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserializer, Serializer};
+
+/*
+    Reads and writes DateTime<Utc> as the array SQF's systemTimeUTC returns:
+        [year, month, day, hour, minute, second, millisecond]
+
+    Use it on any DateTime<Utc> field with serde's with attribute:
+        #[serde(with = "esm_message::arma_time")]
+
+    RFC 3339 strings are still accepted when deserializing, so the field can be read from JSON as well.
+    For systemTime, which is in the server's time zone, use arma_time::local instead.
+
+    A field that has to keep its JSON format can use deserialize_with on its own, as Init and Event do.
+    It is then read from either form but always written as an RFC 3339 string, to_arma included
+*/
+
+/// Writes the time as a systemTimeUTC array
+pub fn serialize<S: Serializer>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_naive(&time.naive_utc(), serializer)
+}
+
+/// Reads the time from a systemTimeUTC array or an RFC 3339 string
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    deserializer.deserialize_any(TimeVisitor { zone: Zone::Utc })
+}
+
+/// Same as arma_time, but the arrays are systemTime's, in the local time zone of the server.
+/// Only correct when this runs on the same machine as Arma, such as in the extension
+pub mod local {
+    use super::*;
+
+    /// Writes the time as a systemTime array
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serialize_naive(&time.with_timezone(&Local).naive_local(), serializer)
+    }
+
+    /// Reads the time from a systemTime array or an RFC 3339 string
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        deserializer.deserialize_any(TimeVisitor { zone: Zone::Local })
+    }
+}
+
+fn serialize_naive<S: Serializer>(time: &NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut array = serializer.serialize_tuple(7)?;
+    array.serialize_element(&time.year())?;
+    array.serialize_element(&time.month())?;
+    array.serialize_element(&time.day())?;
+    array.serialize_element(&time.hour())?;
+    array.serialize_element(&time.minute())?;
+    array.serialize_element(&time.second())?;
+    array.serialize_element(&(time.nanosecond() / 1_000_000).min(999))?;
+    array.end()
+}
+
+#[derive(Clone, Copy)]
+enum Zone {
+    Utc,
+    Local,
+}
+
+struct TimeVisitor {
+    zone: Zone,
+}
+
+impl<'de> Visitor<'de> for TimeVisitor {
+    type Value = DateTime<Utc>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(
+            "an array of [year, month, day, hour, minute, second, millisecond] or an RFC 3339 string",
+        )
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<DateTime<Utc>, E> {
+        DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&Utc))
+            .map_err(|e| {
+                E::custom(format!(
+                    "{value:?} is not a valid RFC 3339 time. Reason: {e}"
+                ))
+            })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DateTime<Utc>, A::Error> {
+        // The milliseconds are optional
+        let mut parts = [0_u32; 7];
+        for (index, part) in parts.iter_mut().enumerate() {
+            match seq.next_element::<u32>()? {
+                Some(p) => *part = p,
+                None if index == 6 => break,
+                None => return Err(de::Error::invalid_length(index, &self)),
+            }
+        }
+
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(8, &self));
+        }
+
+        let [year, month, day, hour, minute, second, millisecond] = parts;
+        let naive = NaiveDate::from_ymd_opt(year as i32, month, day)
+            .and_then(|d| d.and_hms_milli_opt(hour, minute, second, millisecond))
+            .ok_or_else(|| de::Error::custom(format!("{parts:?} is not a valid date and time")))?;
+
+        match self.zone {
+            Zone::Utc => Ok(Utc.from_utc_datetime(&naive)),
+
+            // When clocks go back, the earlier of the two times is used
+            Zone::Local => match Local.from_local_datetime(&naive).earliest() {
+                Some(t) => Ok(t.with_timezone(&Utc)),
+                None => Err(de::Error::custom(format!(
+                    "{parts:?} does not exist in the local time zone"
+                ))),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{from_str, to_arma_string};
+    use crate::{Data, Init};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Times {
+        #[serde(with = "crate::arma_time")]
+        utc: DateTime<Utc>,
+
+        #[serde(with = "crate::arma_time::local")]
+        local: DateTime<Utc>,
+    }
+
+    #[test]
+    fn it_converts_system_time_arrays() {
+        let utc = Utc.with_ymd_and_hms(2024, 2, 29, 23, 59, 1).unwrap()
+            + chrono::Duration::milliseconds(250);
+        let local = Local
+            .with_ymd_and_hms(2024, 7, 4, 12, 30, 0)
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let input = r#"[["utc",[2024,2,29,23,59,1,250]],["local",[2024,7,4,12,30,0,0]]]"#;
+        let times: Times = from_str(input).unwrap();
+        assert_eq!(times, Times { utc, local });
+
+        assert_eq!(to_arma_string(&times).unwrap(), input);
+
+        let json = serde_json::to_string(&times).unwrap();
+        assert_eq!(
+            json,
+            r#"{"utc":[2024,2,29,23,59,1,250],"local":[2024,7,4,12,30,0,0]}"#
+        );
+        assert_eq!(serde_json::from_str::<Times>(&json).unwrap(), times);
+    }
+
+    #[test]
+    fn it_accepts_strings_and_missing_milliseconds() {
+        let input = r#"[["utc","2024-02-29T23:59:01.250Z"],["local",[2024,7,4,12,30,0]]]"#;
+        let times: Times = from_str(input).unwrap();
+        assert_eq!(
+            times.utc,
+            Utc.with_ymd_and_hms(2024, 2, 29, 23, 59, 1).unwrap()
+                + chrono::Duration::milliseconds(250)
+        );
+
+        let input = r#"[["type","init"],["content",[["extension_version","2.0.0"],["price_per_object","10"],["server_name","Server"],["server_start_time",[2024,1,2,3,4,5,6]],["territory_data","[]"],["territory_lifetime","7"],["vg_enabled",false],["vg_max_sizes","[]"]]]]"#;
+        let Data::Init(init) = from_str::<Data>(input).unwrap() else {
+            panic!("Expected Init");
+        };
+
+        assert_eq!(
+            init.server_start_time,
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap() + chrono::Duration::milliseconds(6)
+        );
+
+        // Init is still written out as a string for the bot, and to Arma
+        let json = serde_json::to_value(&init).unwrap();
+        assert_eq!(json["server_start_time"], "2024-01-02T03:04:05.006Z");
+        assert_eq!(serde_json::from_value::<Init>(json).unwrap(), init);
+
+        let arma = to_arma_string(&init).unwrap();
+        assert!(arma.contains(r#"["server_start_time","2024-01-02T03:04:05.006Z"]"#));
+        assert_eq!(from_str::<Init>(&arma).unwrap(), init);
+    }
+
+    #[test]
+    fn it_rejects_invalid_times() {
+        let error = from_str::<Times>(r#"[["utc",[2023,2,29,0,0,0,0]]]"#).unwrap_err();
+        assert_eq!(
+            error.message,
+            "[2023, 2, 29, 0, 0, 0, 0] is not a valid date and time"
+        );
+        assert_eq!(error.path, "utc");
+
+        let error = from_str::<Times>(r#"[["utc",[2023,2,28]]]"#).unwrap_err();
+        assert!(error.message.starts_with("invalid length 3"));
+
+        let error = from_str::<Times>(r#"[["utc",[2023,2,28,0,0,0,0,0]]]"#).unwrap_err();
+        assert!(error.message.starts_with("invalid length 8"));
+
+        let error = from_str::<Times>(r#"[["utc","yesterday"]]"#).unwrap_err();
+        assert!(error
+            .message
+            .starts_with("\"yesterday\" is not a valid RFC 3339 time"));
+    }
+}
@@ -0,0 +1,237 @@
+use rand::random;
+
+use crate::{decrypt_bytes, encrypt_bytes};
+
+const CHALLENGE_SIZE: usize = 32;
+
+// Authenticated with each response. Without it, a response would be a valid handshake packet for any 32 bytes
+// the other side chose to send as a challenge, such as its own X25519 public key
+const RESPONSE_PURPOSE: &[u8] = b"esm_message challenge response";
+
+/*
+    Explicit authentication performed when a server connects, before any Data is processed.
+
+    The bot creates a Challenge and sends `Challenge::as_bytes` to the server. The server parses it with
+    `Challenge::from_bytes` and sends back `Challenge::respond`, which encrypts the challenge with its key.
+    The bot then calls `Challenge::verify` and drops the connection if it fails.
+
+    Challenge (as bytes)
+    [
+        1 byte -> Size of server id (server_id_bytes)
+        # of server_id_bytes -> The server id
+        32 bytes -> The random challenge
+    ]
+
+    Response (as bytes)
+        The challenge encrypted with the server key. Uses the same packet layout as Message::as_bytes,
+        with RESPONSE_PURPOSE authenticated along with it
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    server_id: Vec<u8>,
+    challenge: [u8; CHALLENGE_SIZE],
+}
+
+impl Challenge {
+    pub fn new(server_id: &[u8]) -> Self {
+        Challenge {
+            server_id: server_id.to_vec(),
+            challenge: random(),
+        }
+    }
+
+    pub fn server_id(&self) -> String {
+        String::from_utf8_lossy(&self.server_id).to_string()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![self.server_id.len() as u8];
+        bytes.extend(&*self.server_id);
+        bytes.extend(self.challenge);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Challenge, AuthenticationError> {
+        let Some(id_length) = bytes.first() else {
+            return Err(AuthenticationError::Malformed("Challenge is empty".into()));
+        };
+
+        let id_length = *id_length as usize;
+        if bytes.len() != 1 + id_length + CHALLENGE_SIZE {
+            return Err(AuthenticationError::Malformed(format!(
+                "Expected {} bytes, got {}",
+                1 + id_length + CHALLENGE_SIZE,
+                bytes.len()
+            )));
+        }
+
+        let server_id = bytes[1..=id_length].to_vec();
+
+        let mut challenge = [0_u8; CHALLENGE_SIZE];
+        challenge.copy_from_slice(&bytes[(1 + id_length)..]);
+
+        Ok(Challenge {
+            server_id,
+            challenge,
+        })
+    }
+
+    /// Called by the server to prove it has the server key
+    pub fn respond(&self, server_key: &[u8]) -> Result<Vec<u8>, AuthenticationError> {
+        encrypt_bytes(
+            &self.server_id,
+            &self.challenge,
+            server_key,
+            0,
+            RESPONSE_PURPOSE,
+        )
+        .map_err(AuthenticationError::Malformed)
+    }
+
+    /// Called by the bot to check the server's response
+    pub fn verify(&self, response: &[u8], server_key: &[u8]) -> Result<(), AuthenticationError> {
+        let (server_id, challenge) = match decrypt_bytes(response, server_key, RESPONSE_PURPOSE) {
+            Ok(result) => result,
+            Err(e) => return Err(AuthenticationError::InvalidResponse(e)),
+        };
+
+        if server_id != self.server_id {
+            return Err(AuthenticationError::ServerIdMismatch {
+                expected: self.server_id(),
+                received: String::from_utf8_lossy(&server_id).to_string(),
+            });
+        }
+
+        if challenge != self.challenge {
+            return Err(AuthenticationError::InvalidResponse(
+                "Response does not match the challenge".into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthenticationError {
+    // The challenge or response could not be read
+    Malformed(String),
+
+    // The response was not created with the server's key
+    InvalidResponse(String),
+
+    // The response was created for a different server
+    ServerIdMismatch { expected: String, received: String },
+}
+
+impl std::fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthenticationError::Malformed(e) => write!(f, "Malformed authentication. {e}"),
+            AuthenticationError::InvalidResponse(e) => {
+                write!(f, "Invalid authentication response. {e}")
+            }
+            AuthenticationError::ServerIdMismatch { expected, received } => write!(
+                f,
+                "Authentication response was for {received:?}, expected {expected:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AuthenticationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::random_server_key;
+    use crate::{Handshake, Message};
+    use rand::rngs::OsRng;
+    use x25519_dalek::{EphemeralSecret, PublicKey};
+
+    #[test]
+    fn it_verifies_the_response() {
+        let server_key = random_server_key();
+        let challenge = Challenge::new(b"esm_testing");
+
+        // Server side
+        let received = Challenge::from_bytes(&challenge.as_bytes()).unwrap();
+        assert_eq!(received, challenge);
+        assert_eq!(received.server_id(), "esm_testing");
+
+        let response = received.respond(&server_key).unwrap();
+        assert!(challenge.verify(&response, &server_key).is_ok());
+    }
+
+    #[test]
+    fn it_rejects_the_wrong_key() {
+        let challenge = Challenge::new(b"esm_testing");
+        let response = challenge.respond(&random_server_key()).unwrap();
+
+        assert!(matches!(
+            challenge.verify(&response, &random_server_key()),
+            Err(AuthenticationError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn it_rejects_a_response_to_a_different_challenge() {
+        let server_key = random_server_key();
+        let challenge = Challenge::new(b"esm_testing");
+        let response = Challenge::new(b"esm_testing").respond(&server_key).unwrap();
+
+        assert!(matches!(
+            challenge.verify(&response, &server_key),
+            Err(AuthenticationError::InvalidResponse(_))
+        ));
+
+        let response = Challenge::new(b"esm_other").respond(&server_key).unwrap();
+        assert!(matches!(
+            challenge.verify(&response, &server_key),
+            Err(AuthenticationError::ServerIdMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn it_rejects_malformed_input() {
+        assert!(matches!(
+            Challenge::from_bytes(&[]),
+            Err(AuthenticationError::Malformed(_))
+        ));
+
+        assert!(matches!(
+            Challenge::from_bytes(&[3, 1, 2]),
+            Err(AuthenticationError::Malformed(_))
+        ));
+
+        let challenge = Challenge::new(b"esm_testing");
+        let error = challenge
+            .verify(&[1, 0, 12, 0, 0], &random_server_key())
+            .unwrap_err();
+        assert_eq!(
+            error,
+            AuthenticationError::InvalidResponse(
+                "Failed to decrypt. Packet is too short (5 bytes)".into()
+            )
+        );
+    }
+
+    #[test]
+    fn it_can_not_be_used_as_a_handshake_packet() {
+        let server_key = random_server_key();
+
+        // A challenge chosen to be an X25519 public key
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public_key = PublicKey::from(&secret);
+        let bytes = [&[11], &b"esm_testing"[..], public_key.as_bytes()].concat();
+
+        let response = Challenge::from_bytes(&bytes)
+            .unwrap()
+            .respond(&server_key)
+            .unwrap();
+
+        let handshake = Handshake::new(b"esm_testing");
+        assert!(handshake.complete(&response, &server_key).is_err());
+        assert!(Message::from_bytes(&response, &server_key).is_err());
+    }
+}
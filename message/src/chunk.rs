@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use arma_rs::{FromArma, IntoArma, Value as ArmaValue};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::parser::ParseOptions;
+use crate::Message;
+
+/*
+    Arma cuts off anything an extension returns past its output buffer, so large Arma strings, such as a
+    QueryResult or PostInit from to_arma, are split into pieces that each fit. Every piece is an Arma array:
+        [id, index, total, content]
+
+    id groups the pieces of one string, index starts at 0 and total is how many pieces there are.
+    Joining the contents of all pieces in index order gives back the original string, which can then be parsed.
+    esm_arma stores the pieces it receives by index, so they may arrive in any order.
+
+    Arguments to callExtension are limited as well, so esm_arma splits large messages the same way.
+    The Reassembler collects those pieces and reads the message once it has all of them. It only accepts them in order,
+    and limits how many messages can be waiting on pieces, and for how long, so a peer can not hold on to memory
+*/
+
+/// Arma's callExtension output buffer is 10240 bytes, one of which is the null terminator
+pub const MAX_OUTPUT_LENGTH: usize = 10_239;
+
+/// How long a message can go without receiving its next piece before the Reassembler drops it
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many messages the Reassembler holds pieces for at once before it rejects new ones
+pub const DEFAULT_MAX_PARTIALS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "(String, usize, usize, String)")]
+pub struct Chunk {
+    pub id: String,
+    pub index: usize,
+    pub total: usize,
+    pub content: String,
+}
+
+impl IntoArma for Chunk {
+    fn to_arma(&self) -> ArmaValue {
+        ArmaValue::Array(vec![
+            ArmaValue::String(self.id.clone()),
+            ArmaValue::Number(self.index as f64),
+            ArmaValue::Number(self.total as f64),
+            ArmaValue::String(self.content.clone()),
+        ])
+    }
+}
+
+impl FromArma for Chunk {
+    fn from_arma(string: String) -> Result<Self, String> {
+        crate::parser::Parser::from_arma(&string)
+    }
+}
+
+// esm_arma sends pieces in the same array form
+impl From<(String, usize, usize, String)> for Chunk {
+    fn from((id, index, total, content): (String, usize, usize, String)) -> Self {
+        Chunk {
+            id,
+            index,
+            total,
+            content,
+        }
+    }
+}
+
+/// Splits the input into pieces that each fit in Arma's output buffer once converted with to_arma.
+/// The input is always returned as at least one piece, even when it is empty
+pub fn split(id: &str, input: &str) -> Result<Vec<Chunk>, String> {
+    split_with_limit(id, input, MAX_OUTPUT_LENGTH)
+}
+
+/// Same as split, but every piece is at most max_length bytes once converted with to_arma
+pub fn split_with_limit(id: &str, input: &str, max_length: usize) -> Result<Vec<Chunk>, String> {
+    // The index and total take more room as the number of pieces grows, so split again until they fit
+    let mut digits = 1;
+    loop {
+        // ["id",index,total,"content"]
+        let overhead = escaped_length(id) + 2 * digits + 9;
+
+        // Any one character takes at most 4 bytes
+        let budget = match max_length.checked_sub(overhead) {
+            Some(budget) if budget >= 4 => budget,
+            _ => {
+                return Err(format!(
+                    "[esm_message::chunk::split] {max_length} bytes is too small to hold a piece of {id:?}"
+                ))
+            }
+        };
+
+        let contents = split_content(input, budget);
+        if contents.len().to_string().len() > digits {
+            digits += 1;
+            continue;
+        }
+
+        let total = contents.len();
+        return Ok(contents
+            .into_iter()
+            .enumerate()
+            .map(|(index, content)| Chunk {
+                id: id.to_string(),
+                index,
+                total,
+                content: content.to_string(),
+            })
+            .collect());
+    }
+}
+
+/// Collects the pieces of messages sent from Arma, keyed by message id.
+/// esm_arma sends the message as one array, [id, type, data, metadata, errors], split with the same format as split.
+/// Pieces are sent one call at a time, so they have to arrive in order
+#[derive(Debug)]
+pub struct Reassembler {
+    timeout: Duration,
+    max_length: usize,
+    max_partials: usize,
+    partials: HashMap<String, Partial>,
+}
+
+#[derive(Debug)]
+struct Partial {
+    total: usize,
+    received: usize,
+    content: String,
+    updated_at: Instant,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Reassembler::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Reassembler {
+            timeout,
+            max_length: ParseOptions::default().max_input_length,
+            max_partials: DEFAULT_MAX_PARTIALS,
+            partials: HashMap::new(),
+        }
+    }
+
+    /// Messages longer than this, in bytes, are dropped. Defaults to ParseOptions::max_input_length
+    pub fn set_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// The most messages that can be waiting on more pieces at once. Defaults to DEFAULT_MAX_PARTIALS
+    pub fn set_max_partials(mut self, max_partials: usize) -> Self {
+        self.max_partials = max_partials;
+        self
+    }
+
+    /// The number of messages that are waiting on more pieces
+    pub fn len(&self) -> usize {
+        self.partials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.partials.is_empty()
+    }
+
+    /// Adds the piece, returning the message once all of its pieces have arrived.
+    /// Any error drops the pieces received so far, as the message can no longer be completed
+    pub fn receive(&mut self, chunk: Chunk) -> Result<Option<Message>, String> {
+        self.receive_at(chunk, Instant::now())
+    }
+
+    /// Drops every message that has not received a piece within the timeout and returns their ids.
+    /// receive does this as well, so this is only needed to free the memory sooner
+    pub fn remove_stale(&mut self) -> Vec<String> {
+        self.remove_stale_at(Instant::now())
+    }
+
+    fn receive_at(&mut self, chunk: Chunk, now: Instant) -> Result<Option<Message>, String> {
+        self.remove_stale_at(now);
+
+        let id = chunk.id.clone();
+        let content = match self.add(chunk, now) {
+            Ok(Some(content)) => content,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                self.partials.remove(&id);
+                return Err(e);
+            }
+        };
+
+        let message = Message::from_arma_str(&content)?;
+        if Uuid::parse_str(&id).ok() != Some(message.id) {
+            return Err(format!(
+                "[esm_message::chunk::receive] The pieces of {id:?} contain message {}",
+                message.id
+            ));
+        }
+
+        Ok(Some(message))
+    }
+
+    // Returns the full content once the last piece is added
+    fn add(&mut self, chunk: Chunk, now: Instant) -> Result<Option<String>, String> {
+        let Chunk {
+            id,
+            index,
+            total,
+            content,
+        } = chunk;
+
+        if index >= total {
+            return Err(format!(
+                "[esm_message::chunk::receive] Piece {index} of {id:?} is out of range, it only has {total} pieces"
+            ));
+        }
+
+        // The first piece always starts the message over, in case esm_arma is sending it again
+        if index == 0 {
+            if !self.partials.contains_key(&id) && self.partials.len() >= self.max_partials {
+                return Err(format!(
+                    "[esm_message::chunk::receive] Can not start {id:?}, there are already {} messages waiting on pieces",
+                    self.partials.len()
+                ));
+            }
+
+            self.partials.insert(
+                id.clone(),
+                Partial {
+                    total,
+                    received: 0,
+                    content: String::new(),
+                    updated_at: now,
+                },
+            );
+        }
+
+        let Some(partial) = self.partials.get_mut(&id) else {
+            return Err(format!(
+                "[esm_message::chunk::receive] Received piece {index} of {id:?} without the pieces before it"
+            ));
+        };
+
+        if total != partial.total {
+            return Err(format!(
+                "[esm_message::chunk::receive] Piece {index} of {id:?} says it has {total} pieces, but the first said {}",
+                partial.total
+            ));
+        }
+
+        if index != partial.received {
+            return Err(format!(
+                "[esm_message::chunk::receive] Expected piece {} of {id:?}, got piece {index}",
+                partial.received
+            ));
+        }
+
+        if partial.content.len() + content.len() > self.max_length {
+            return Err(format!(
+                "[esm_message::chunk::receive] {id:?} is longer than the limit of {} bytes",
+                self.max_length
+            ));
+        }
+
+        partial.content.push_str(&content);
+        partial.received += 1;
+        partial.updated_at = now;
+
+        if partial.received < partial.total {
+            return Ok(None);
+        }
+
+        Ok(self.partials.remove(&id).map(|p| p.content))
+    }
+
+    fn remove_stale_at(&mut self, now: Instant) -> Vec<String> {
+        let stale: Vec<String> = self
+            .partials
+            .iter()
+            .filter(|(_, p)| now.duration_since(p.updated_at) > self.timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &stale {
+            self.partials.remove(id);
+        }
+
+        stale
+    }
+}
+
+// Splits between characters, so no piece ends in the middle of one or of an escaped quote
+fn split_content(input: &str, budget: usize) -> Vec<&str> {
+    let mut contents = vec![];
+    let mut start = 0;
+    let mut length = 0;
+
+    for (index, character) in input.char_indices() {
+        let cost = escaped_char_length(character);
+        if length + cost > budget {
+            contents.push(&input[start..index]);
+            start = index;
+            length = 0;
+        }
+
+        length += cost;
+    }
+
+    contents.push(&input[start..]);
+    contents
+}
+
+// Quotes are doubled inside of Arma strings
+fn escaped_char_length(character: char) -> usize {
+    if character == '"' {
+        2
+    } else {
+        character.len_utf8()
+    }
+}
+
+fn escaped_length(input: &str) -> usize {
+    input.chars().map(escaped_char_length).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::from_arma_value;
+    use serde_json::Value as JSONValue;
+
+    /*
+        The SQF side of the contract. esm_arma stores each piece as it arrives:
+            (parseSimpleArray _piece) params ["_id", "_index", "_total", "_content"];
+            private _parts = ESM_Chunks getOrDefault [_id, [], true];
+            _parts set [_index, _content];
+
+        And once it has all of them, joins and parses the result:
+            if ({ !isNil "_x" } count _parts == _total) then {
+                ESM_Chunks deleteAt _id;
+                parseSimpleArray (_parts joinString "")
+            };
+    */
+    fn reassemble(pieces: &[String]) -> String {
+        let mut parts: Vec<Option<String>> = vec![];
+        let mut ids = vec![];
+
+        for piece in pieces {
+            let JSONValue::Array(piece) = from_arma_value(piece).unwrap() else {
+                panic!("Expected an array");
+            };
+
+            let [id, index, total, content] = &piece[..] else {
+                panic!("Expected 4 elements");
+            };
+
+            let index = index.as_u64().unwrap() as usize;
+            let total = total.as_u64().unwrap() as usize;
+            parts.resize(total, None);
+            parts[index] = Some(content.as_str().unwrap().to_string());
+            ids.push(id.as_str().unwrap().to_string());
+        }
+
+        assert!(ids.iter().all(|id| *id == ids[0]));
+        parts.into_iter().map(Option::unwrap).collect()
+    }
+
+    #[test]
+    fn it_splits_into_pieces_that_fit() {
+        let input = format!(
+            "[{}]",
+            (0..2_000)
+                .map(|i| format!(r#"["territory_{i}","Ünïcödé ""{i}"" 🚀"]"#))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        let chunks = split("a9f2", &input).unwrap();
+        assert!(chunks.len() > 1);
+
+        let mut pieces: Vec<String> = chunks
+            .iter()
+            .map(|c| {
+                assert_eq!(c.total, chunks.len());
+                c.to_arma().to_string()
+            })
+            .collect();
+
+        assert!(pieces.iter().all(|p| p.len() <= MAX_OUTPUT_LENGTH));
+
+        // Order does not matter
+        pieces.reverse();
+        assert_eq!(reassemble(&pieces), input);
+    }
+
+    #[test]
+    fn it_grows_the_header_with_the_number_of_pieces() {
+        let input = "\"".repeat(500);
+        let chunks = split_with_limit("id", &input, 24).unwrap();
+
+        // Room for 5 quotes makes 100 pieces and 4 makes 125, neither of which fit in the header they were split for
+        assert_eq!(chunks.len(), 167);
+        assert_eq!(chunks[0].content, "\"\"\"");
+
+        let pieces: Vec<String> = chunks.iter().map(|c| c.to_arma().to_string()).collect();
+        assert_eq!(pieces[0], r#"["id",0,167,""""""""]"#);
+        assert!(pieces.iter().all(|p| p.len() <= 24));
+        assert_eq!(reassemble(&pieces), input);
+    }
+
+    #[test]
+    fn it_always_returns_a_piece() {
+        let chunks = split("id", "").unwrap();
+        assert_eq!(
+            chunks,
+            vec![Chunk {
+                id: "id".into(),
+                index: 0,
+                total: 1,
+                content: String::new()
+            }]
+        );
+        assert_eq!(chunks[0].to_arma().to_string(), r#"["id",0,1,""]"#);
+
+        let error = split_with_limit("id", "[]", 15).unwrap_err();
+        assert_eq!(
+            error,
+            "[esm_message::chunk::split] 15 bytes is too small to hold a piece of \"id\""
+        );
+        assert!(split_with_limit("id", "[]", 17).is_ok());
+    }
+
+    /*
+        What esm_arma does with a message too long for one call:
+            private _message = str [_id, _type, _data, _metadata, _errors];
+            private _total = ceil (count _message / _size);
+            for "_index" from 0 to (_total - 1) do {
+                "esm" callExtension ["receive_chunk", [[_id, _index, _total, _message select [_index * _size, _size]]]];
+            };
+    */
+    fn pieces(id: Uuid) -> Vec<Chunk> {
+        let message = format!(
+            r#"["{id}","test",[["type","test"],["content",[["foo","test""ing"]]]],[["type","test"],["content",[["foo","""nested"""]]]],[[["type","code"],["content","CODING"]]]]"#
+        );
+
+        // Goes through Arma's text form, the same way callExtension passes it
+        split_with_limit(&id.to_string(), &message, 70)
+            .unwrap()
+            .into_iter()
+            .map(|c| Chunk::from_arma(c.to_arma().to_string()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_reassembles_messages() {
+        let id = Uuid::new_v4();
+        let mut reassembler = Reassembler::default();
+
+        let mut pieces = pieces(id);
+        let last = pieces.pop().unwrap();
+        assert!(pieces.len() > 2);
+
+        for piece in pieces {
+            assert!(reassembler.receive(piece).unwrap().is_none());
+        }
+        assert_eq!(reassembler.len(), 1);
+
+        let message = reassembler.receive(last).unwrap().unwrap();
+        assert!(reassembler.is_empty());
+
+        assert_eq!(message.id, id);
+        assert_eq!(message.message_type, crate::Type::Test);
+        assert_eq!(
+            message.data,
+            crate::Data::Test(crate::data::Test {
+                foo: "test\"ing".into()
+            })
+        );
+        assert_eq!(
+            message.metadata,
+            crate::Metadata::Test(crate::metadata::Test {
+                foo: "\"nested\"".into()
+            })
+        );
+        assert_eq!(
+            message.errors,
+            vec![crate::Error::new(crate::ErrorType::Code, "CODING".into())]
+        );
+    }
+
+    #[test]
+    fn it_rejects_pieces_out_of_order() {
+        let id = Uuid::new_v4();
+        let pieces = pieces(id);
+        let mut reassembler = Reassembler::default();
+
+        let error = reassembler.receive(pieces[1].clone()).unwrap_err();
+        assert_eq!(
+            error,
+            format!("[esm_message::chunk::receive] Received piece 1 of \"{id}\" without the pieces before it")
+        );
+
+        reassembler.receive(pieces[0].clone()).unwrap();
+        let error = reassembler.receive(pieces[2].clone()).unwrap_err();
+        assert_eq!(
+            error,
+            format!("[esm_message::chunk::receive] Expected piece 1 of \"{id}\", got piece 2")
+        );
+
+        // The message can not be completed anymore
+        assert!(reassembler.is_empty());
+        assert!(reassembler.receive(pieces[1].clone()).is_err());
+
+        reassembler.receive(pieces[0].clone()).unwrap();
+        let error = reassembler
+            .receive(Chunk {
+                total: 2,
+                ..pieces[1].clone()
+            })
+            .unwrap_err();
+        assert!(error.contains("says it has 2 pieces, but the first said"));
+
+        let error = reassembler
+            .receive(Chunk {
+                index: 5,
+                total: 5,
+                ..pieces[0].clone()
+            })
+            .unwrap_err();
+        assert!(error.contains("Piece 5 of"));
+
+        // Sending the first piece again starts over
+        let mut reassembler = Reassembler::default();
+        reassembler.receive(pieces[0].clone()).unwrap();
+        reassembler.receive(pieces[1].clone()).unwrap();
+
+        let message = pieces
+            .into_iter()
+            .map(|p| reassembler.receive(p).unwrap())
+            .last()
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.id, id);
+    }
+
+    #[test]
+    fn it_drops_stale_and_oversized_messages() {
+        let id = Uuid::new_v4();
+        let pieces = pieces(id);
+        let now = Instant::now();
+
+        let mut reassembler = Reassembler::new(Duration::from_secs(5));
+        reassembler.receive_at(pieces[0].clone(), now).unwrap();
+        reassembler
+            .receive_at(pieces[1].clone(), now + Duration::from_secs(4))
+            .unwrap();
+
+        assert!(reassembler
+            .remove_stale_at(now + Duration::from_secs(9))
+            .is_empty());
+        assert_eq!(
+            reassembler.remove_stale_at(now + Duration::from_secs(10)),
+            vec![id.to_string()]
+        );
+
+        assert!(reassembler
+            .receive_at(pieces[2].clone(), now + Duration::from_secs(10))
+            .is_err());
+
+        let mut reassembler = Reassembler::default().set_max_length(30);
+        reassembler.receive(pieces[0].clone()).unwrap();
+        let error = reassembler.receive(pieces[1].clone()).unwrap_err();
+        assert_eq!(
+            error,
+            format!("[esm_message::chunk::receive] \"{id}\" is longer than the limit of 30 bytes")
+        );
+
+        // Pieces must belong to the message they say they are for
+        let mut reassembler = Reassembler::default();
+        let other = Uuid::new_v4();
+        let result = pieces
+            .into_iter()
+            .map(|p| {
+                reassembler.receive(Chunk {
+                    id: other.to_string(),
+                    ..p
+                })
+            })
+            .last()
+            .unwrap();
+        assert_eq!(
+            result.unwrap_err(),
+            format!("[esm_message::chunk::receive] The pieces of \"{other}\" contain message {id}")
+        );
+    }
+
+    #[test]
+    fn it_limits_how_many_messages_are_waiting() {
+        let first = pieces(Uuid::new_v4());
+        let second = pieces(Uuid::new_v4());
+        let third = pieces(Uuid::new_v4());
+        let now = Instant::now();
+
+        let mut reassembler = Reassembler::new(Duration::from_secs(5)).set_max_partials(2);
+        reassembler.receive_at(first[0].clone(), now).unwrap();
+        reassembler.receive_at(second[0].clone(), now).unwrap();
+
+        let error = reassembler.receive_at(third[0].clone(), now).unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "[esm_message::chunk::receive] Can not start {:?}, there are already 2 messages waiting on pieces",
+                third[0].id
+            )
+        );
+        assert_eq!(reassembler.len(), 2);
+
+        // Messages already waiting can still start over and continue
+        reassembler.receive_at(first[0].clone(), now).unwrap();
+        reassembler.receive_at(first[1].clone(), now).unwrap();
+
+        // Once the others time out there is room again
+        reassembler
+            .receive_at(third[0].clone(), now + Duration::from_secs(6))
+            .unwrap();
+        assert_eq!(reassembler.len(), 1);
+    }
+}
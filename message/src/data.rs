@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use crate::parser::SerializeError;
 use crate::NumberString;
 use arma_rs::{FromArma, IntoArma, Value as ArmaValue};
 use chrono::{DateTime, Utc};
@@ -73,6 +74,19 @@ impl IntoArma for Data {
     }
 }
 
+impl Data {
+    /// Same as to_arma, but keeps the type the data is: [["type", "init"], ["content", [...]]].
+    /// This is the form FromArma reads, so from_arma(data.to_arma_tagged()?.to_string()) gives back the same data
+    pub fn to_arma_tagged(&self) -> Result<ArmaValue, SerializeError> {
+        crate::parser::to_arma_value(self)
+    }
+
+    /// Reads the tagged form to_arma_tagged writes, straight from the arma_rs::Value an extension call received
+    pub fn from_arma_value(input: &ArmaValue) -> Result<Self, String> {
+        crate::parser::Parser::from_value(input)
+    }
+}
+
 impl FromArma for Data {
     fn from_arma(string: String) -> Result<Self, String> {
         crate::parser::Parser::from_arma(&string)
@@ -108,7 +122,12 @@ pub struct Init {
     pub extension_version: String,
     pub price_per_object: NumberString,
     pub server_name: String,
+
+    // Arma can send either systemTimeUTC or a string. Only reading is changed, so the bot always receives
+    // a string and to_arma writes one too. Either way it reads back the same
+    #[serde(deserialize_with = "crate::arma_time::deserialize")]
     pub server_start_time: DateTime<Utc>,
+
     pub territory_data: String,
     pub territory_lifetime: NumberString,
     pub vg_enabled: bool,
@@ -119,11 +138,11 @@ impl Default for Init {
     fn default() -> Self {
         Init {
             extension_version: "".into(),
-            price_per_object: "".into(),
+            price_per_object: NumberString::default(),
             server_name: "".into(),
             server_start_time: Utc::now(),
             territory_data: "".into(),
-            territory_lifetime: "".into(),
+            territory_lifetime: NumberString::default(),
             vg_enabled: false,
             vg_max_sizes: "".into(),
         }
@@ -325,10 +344,12 @@ pub struct PostInit {
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ImplIntoArma)]
 pub struct Reward {
+    #[serde(serialize_with = "crate::sorted::serialize_option")]
     pub items: Option<HashMap<String, NumberString>>,
     pub locker_poptabs: Option<NumberString>,
     pub player_poptabs: Option<NumberString>,
     pub respect: Option<NumberString>,
+    #[serde(serialize_with = "crate::sorted::serialize_option_list")]
     pub vehicles: Option<Vec<HashMap<String, String>>>,
 }
 
@@ -346,6 +367,9 @@ pub struct SqfResult {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ImplIntoArma)]
 pub struct Event {
     pub event_type: String,
+
+    // Read the same way as Init's server_start_time, and written as a string
+    #[serde(deserialize_with = "crate::arma_time::deserialize")]
     pub triggered_at: DateTime<Utc>,
 }
 
@@ -369,6 +393,7 @@ pub struct Event {
 // reset_payment_counter
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, ImplIntoArma)]
 pub struct Query {
+    #[serde(serialize_with = "crate::sorted::serialize")]
     pub arguments: HashMap<String, String>,
     pub name: String,
 }
@@ -415,17 +440,84 @@ mod tests {
         assert_eq!(data.to_arma().to_string(), "[[\"foo\",\"bar\"]]");
 
         let mut items = HashMap::new();
-        items.insert("key_1".to_string(), "value_1".to_string());
+        items.insert("key_1".to_string(), 4.into());
 
         let data = Reward {
             items: Some(items),
-            locker_poptabs: Some("1".to_string()),
-            player_poptabs: Some("3".to_string()),
-            respect: Some("2".to_string()),
+            locker_poptabs: Some(1.into()),
+            player_poptabs: Some(3.into()),
+            respect: Some(2.into()),
             vehicles: None,
         };
 
-        assert_eq!(data.to_arma().to_string(), "[[\"items\",[[\"key_1\",\"value_1\"]]],[\"locker_poptabs\",\"1\"],[\"player_poptabs\",\"3\"],[\"respect\",\"2\"],[\"vehicles\",null]]");
+        assert_eq!(data.to_arma().to_string(), "[[\"items\",[[\"key_1\",\"4\"]]],[\"locker_poptabs\",\"1\"],[\"player_poptabs\",\"3\"],[\"respect\",\"2\"],[\"vehicles\",null]]");
+    }
+
+    #[test]
+    fn it_converts_number_strings_to_arma() {
+        let mut items = HashMap::new();
+        items.insert("key_1".to_string(), NumberString::from(4));
+
+        let data = Reward {
+            items: Some(items),
+            locker_poptabs: Some(NumberString::from(1_u64)),
+            player_poptabs: Some("1.5e3".parse().unwrap()),
+            respect: Some(NumberString::from(-2)),
+            vehicles: None,
+        };
+
+        assert_eq!(data.to_arma().to_string(), "[[\"items\",[[\"key_1\",\"4\"]]],[\"locker_poptabs\",\"1\"],[\"player_poptabs\",\"1500\"],[\"respect\",\"-2\"],[\"vehicles\",null]]");
+    }
+
+    #[test]
+    fn it_sorts_map_fields_in_arma_output() {
+        let items: HashMap<String, NumberString> = ["c", "a", "d", "b", "f", "e"]
+            .iter()
+            .map(|key| (key.to_string(), NumberString::from(1)))
+            .collect();
+
+        let data = Reward {
+            items: Some(items),
+            locker_poptabs: None,
+            player_poptabs: None,
+            respect: None,
+            vehicles: Some(vec![HashMap::from([
+                (
+                    "vehicle_class".to_string(),
+                    "Exile_Car_Hatchback".to_string(),
+                ),
+                ("location".to_string(), "player_decides".to_string()),
+            ])]),
+        };
+
+        let expected = r#"[["items",[["a","1"],["b","1"],["c","1"],["d","1"],["e","1"],["f","1"]]],["locker_poptabs",null],["player_poptabs",null],["respect",null],["vehicles",[[["location","player_decides"],["vehicle_class","Exile_Car_Hatchback"]]]]]"#;
+        assert_eq!(data.to_arma().to_string(), expected);
+
+        let query = Query {
+            arguments: HashMap::from([
+                ("uid".to_string(), "76561198037177305".to_string()),
+                ("territory_id".to_string(), "1".to_string()),
+            ]),
+            name: "territory_info".into(),
+        };
+
+        assert_eq!(
+            query.to_arma().to_string(),
+            r#"[["arguments",[["territory_id","1"],["uid","76561198037177305"]]],["name","territory_info"]]"#
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_type_in_tagged_output() {
+        let data = Data::Test(Test { foo: "bar".into() });
+        assert_eq!(
+            data.to_arma_tagged().unwrap().to_string(),
+            r#"[["type","test"],["content",[["foo","bar"]]]]"#
+        );
+        assert_eq!(
+            Data::Ping.to_arma_tagged().unwrap().to_string(),
+            r#"[["type","ping"]]"#
+        );
     }
 
     #[test]
@@ -433,11 +525,11 @@ mod tests {
         assert!(Init::default().validate().is_err());
         assert!(Init {
             extension_version: "version".into(),
-            price_per_object: "5".into(),
+            price_per_object: 5.into(),
             server_name: "server name".into(),
             server_start_time: Utc::now(),
             territory_data: "[]".into(),
-            territory_lifetime: "7".into(),
+            territory_lifetime: 7.into(),
             vg_enabled: false,
             vg_max_sizes: "[]".into(),
         }
@@ -447,11 +539,11 @@ mod tests {
         assert_eq!(
             Init {
                 extension_version: "".into(),
-                price_per_object: "-1".into(),
+                price_per_object: (-1).into(),
                 server_name: "server name".into(),
                 server_start_time: Utc::now(),
                 territory_data: "".into(),
-                territory_lifetime: "7".into(),
+                territory_lifetime: 7.into(),
                 vg_enabled: false,
                 vg_max_sizes: "[]".into(),
             }
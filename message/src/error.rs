@@ -1,5 +1,5 @@
+use arma_rs::Value as ArmaValue;
 use serde::{Deserialize, Serialize};
-use serde_json::Value as JSONValue;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Error {
@@ -20,36 +20,22 @@ impl Error {
     }
 
     pub fn from_arma(input: String) -> Result<Vec<Self>, String> {
-        let input: JSONValue = match serde_json::from_str(&input) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(format!(
-                    "[esm_message::error::from_arma] Failed to convert input into JSONValue. Reason: {e}. Input: {input:?}"
-                ))
-            }
-        };
-
-        let errors = crate::parser::validate_content(&input);
-        let error_array = match errors.as_array() {
-            Some(e) => e,
-            None => return Err(format!("[esm_message::error::from_arma] Failed to convert validated errors to array. Errors: \"{errors:?}\"")),
-        };
-
-        let mut errors: Vec<Error> = Vec::new();
-        for error in error_array {
-            let error = crate::parser::validate_content(error);
-            let json = match serde_json::to_string(&error) {
-                Ok(j) => j,
-                Err(e) => return Err(format!("[esm_message::error::from_arma] Failed to convert to final JSON. Reason: {e}. Error: \"{error:?}\"")),
-            };
-
-            match serde_json::from_str(&json) {
-                Ok(e) => errors.push(e),
-                Err(e) => return Err(format!("[esm_message::error::from_arma] Failed to convert to Error. Reason: {e}. Error: \"{error:?}\" ")),
-            };
+        match crate::parser::from_str(&input) {
+            Ok(errors) => Ok(errors),
+            Err(e) => Err(format!(
+                "[esm_message::error::from_arma] Failed to convert to Error. {e}"
+            )),
         }
+    }
 
-        Ok(errors)
+    /// Reads the list of errors that makes up the last element of a message, as an arma_rs::Value
+    pub fn from_arma_value(input: &ArmaValue) -> Result<Vec<Self>, String> {
+        match crate::parser::from_value(input) {
+            Ok(errors) => Ok(errors),
+            Err(e) => Err(format!(
+                "[esm_message::error::from_arma_value] Failed to convert to Error. {e}"
+            )),
+        }
     }
 }
 
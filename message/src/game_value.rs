@@ -0,0 +1,400 @@
+use std::fmt;
+use std::str::FromStr;
+
+use arma_rs::{FromArma, IntoArma, Value as ArmaValue};
+use message_proc::ImplIntoArma;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/*
+    Typed versions of the values SQF passes around, so they do not have to be sent as raw strings.
+    Each one reads and writes the same form SQF's str gives it:
+        Position  -> [x, y, z]
+        Side      -> "WEST", "EAST", "GUER", "CIV", ...
+        ObjectRef -> "B Alpha 1-1:1", "2:345"
+        ArmaColor -> [r, g, b, a]
+*/
+
+/// A position or vector, such as from getPosATL or vectorDir. [x, y] is read with a z of 0
+#[derive(Debug, Clone, Copy, Default, PartialEq, ImplIntoArma)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Position {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
+        Position { x, y, z }
+    }
+
+    /// The distance between the two positions, the same as SQF's distance
+    pub fn distance(&self, other: &Position) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2))
+            .sqrt()
+    }
+
+    /// The distance between the two positions, ignoring height. The same as SQF's distance2D
+    pub fn distance_2d(&self, other: &Position) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+impl FromArma for Position {
+    fn from_arma(string: String) -> Result<Self, String> {
+        crate::parser::Parser::from_arma(&string)
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut array = serializer.serialize_tuple(3)?;
+        array.serialize_element(&self.x)?;
+        array.serialize_element(&self.y)?;
+        array.serialize_element(&self.z)?;
+        array.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let [x, y, z] = deserializer.deserialize_seq(NumbersVisitor {
+            expecting: "an array of [x, y] or [x, y, z]",
+            defaults: [None, None, Some(0.0)],
+        })?;
+
+        Ok(Position { x, y, z })
+    }
+}
+
+/// An RGBA color, each part from 0 to 1. [r, g, b] is read with an alpha of 1
+#[derive(Debug, Clone, Copy, PartialEq, ImplIntoArma)]
+pub struct ArmaColor {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl ArmaColor {
+    pub fn new(r: f64, g: f64, b: f64, a: f64) -> Self {
+        ArmaColor { r, g, b, a }
+    }
+
+    /// The color as #RRGGBB, ignoring the alpha. Used by Discord embeds
+    pub fn to_hex(&self) -> String {
+        let part = |p: f64| (p * 255.0).round() as u8;
+        format!(
+            "#{:02X}{:02X}{:02X}",
+            part(self.r),
+            part(self.g),
+            part(self.b)
+        )
+    }
+}
+
+impl Default for ArmaColor {
+    fn default() -> Self {
+        ArmaColor::new(1.0, 1.0, 1.0, 1.0)
+    }
+}
+
+impl FromArma for ArmaColor {
+    fn from_arma(string: String) -> Result<Self, String> {
+        crate::parser::Parser::from_arma(&string)
+    }
+}
+
+impl Serialize for ArmaColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut array = serializer.serialize_tuple(4)?;
+        array.serialize_element(&self.r)?;
+        array.serialize_element(&self.g)?;
+        array.serialize_element(&self.b)?;
+        array.serialize_element(&self.a)?;
+        array.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for ArmaColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let parts = deserializer.deserialize_seq(NumbersVisitor {
+            expecting: "an array of [r, g, b] or [r, g, b, a]",
+            defaults: [None, None, None, Some(1.0)],
+        })?;
+
+        if let Some(part) = parts.iter().find(|p| !(0.0..=1.0).contains(*p)) {
+            return Err(de::Error::custom(format!(
+                "{part} is not a valid color, each part must be from 0 to 1"
+            )));
+        }
+
+        let [r, g, b, a] = parts;
+        Ok(ArmaColor { r, g, b, a })
+    }
+}
+
+// Reads an array of numbers. The ones with a default can be left off the end
+struct NumbersVisitor<const N: usize> {
+    expecting: &'static str,
+    defaults: [Option<f64>; N],
+}
+
+impl<'de, const N: usize> Visitor<'de> for NumbersVisitor<N> {
+    type Value = [f64; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.expecting)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[f64; N], A::Error> {
+        let mut numbers = [0.0; N];
+        for (index, number) in numbers.iter_mut().enumerate() {
+            *number = match (seq.next_element::<f64>()?, self.defaults[index]) {
+                (Some(n), _) => n,
+                (None, Some(default)) => default,
+                (None, None) => return Err(de::Error::invalid_length(index, &self)),
+            };
+        }
+
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+
+        Ok(numbers)
+    }
+}
+
+/// A side, written the way SQF's str writes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    West,
+    East,
+    Independent,
+    Civilian,
+    Logic,
+    Unknown,
+    Enemy,
+    Friendly,
+    AmbientLife,
+    Empty,
+}
+
+impl Side {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::West => "WEST",
+            Side::East => "EAST",
+            Side::Independent => "GUER",
+            Side::Civilian => "CIV",
+            Side::Logic => "LOGIC",
+            Side::Unknown => "UNKNOWN",
+            Side::Enemy => "ENEMY",
+            Side::Friendly => "FRIENDLY",
+            Side::AmbientLife => "AMBIENT LIFE",
+            Side::Empty => "EMPTY",
+        }
+    }
+}
+
+// Also accepts the names of the SQF commands that return each side, such as blufor or resistance
+impl FromStr for Side {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let side = match input.trim().to_uppercase().as_str() {
+            "WEST" | "BLUFOR" => Side::West,
+            "EAST" | "OPFOR" => Side::East,
+            "GUER" | "INDEPENDENT" | "RESISTANCE" => Side::Independent,
+            "CIV" | "CIVILIAN" => Side::Civilian,
+            "LOGIC" | "SIDELOGIC" => Side::Logic,
+            "UNKNOWN" | "SIDEUNKNOWN" => Side::Unknown,
+            "ENEMY" | "SIDEENEMY" => Side::Enemy,
+            "FRIENDLY" | "SIDEFRIENDLY" => Side::Friendly,
+            "AMBIENT LIFE" | "SIDEAMBIENTLIFE" => Side::AmbientLife,
+            "EMPTY" | "SIDEEMPTY" => Side::Empty,
+            _ => return Err(format!("{input:?} is not a valid side")),
+        };
+
+        Ok(side)
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl IntoArma for Side {
+    fn to_arma(&self) -> ArmaValue {
+        ArmaValue::String(self.as_str().to_string())
+    }
+}
+
+impl FromArma for Side {
+    fn from_arma(string: String) -> Result<Self, String> {
+        crate::parser::Parser::from_arma(&string)
+    }
+}
+
+impl Serialize for Side {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Side {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// An object, as SQF's str writes it: "B Alpha 1-1:1" for a unit or "2:345" from netId.
+/// objNull arrives as nil, so use Option<ObjectRef> where the object may not exist
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, ImplIntoArma)]
+#[serde(transparent)]
+pub struct ObjectRef(String);
+
+impl ObjectRef {
+    pub fn new<S: Into<String>>(reference: S) -> Self {
+        ObjectRef(reference.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The two numbers of a netId, such as "2:345". None for anything else
+    pub fn net_id(&self) -> Option<(u32, u32)> {
+        let (owner, id) = self.0.split_once(':')?;
+        Some((owner.parse().ok()?, id.parse().ok()?))
+    }
+
+    /// The side of a unit from the letter its group starts with, such as the B in "B Alpha 1-1:1"
+    pub fn side(&self) -> Option<Side> {
+        let (letter, _) = self.0.split_once(' ')?;
+        match letter {
+            "B" => Some(Side::West),
+            "O" => Some(Side::East),
+            "I" => Some(Side::Independent),
+            "C" => Some(Side::Civilian),
+            _ => None,
+        }
+    }
+}
+
+impl FromArma for ObjectRef {
+    fn from_arma(string: String) -> Result<Self, String> {
+        crate::parser::Parser::from_arma(&string)
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{from_str, to_arma_string};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Spawn {
+        position: Position,
+        direction: Position,
+        side: Side,
+        object: Option<ObjectRef>,
+        color: ArmaColor,
+    }
+
+    #[test]
+    fn it_converts_game_values() {
+        let input = r#"[["position",[14599.2,16797.5,0.00143814]],["direction",[0,1]],["side","GUER"],["object","B Alpha 1-1:1"],["color",[1,0.5,0]]]"#;
+        let spawn: Spawn = from_str(input).unwrap();
+
+        assert_eq!(
+            spawn,
+            Spawn {
+                position: Position::new(14599.2, 16797.5, 0.00143814),
+                direction: Position::new(0.0, 1.0, 0.0),
+                side: Side::Independent,
+                object: Some(ObjectRef::new("B Alpha 1-1:1")),
+                color: ArmaColor::new(1.0, 0.5, 0.0, 1.0),
+            }
+        );
+
+        assert_eq!(
+            to_arma_string(&spawn).unwrap(),
+            r#"[["position",[14599.2,16797.5,0.00143814]],["direction",[0,1,0]],["side","GUER"],["object","B Alpha 1-1:1"],["color",[1,0.5,0,1]]]"#
+        );
+
+        let json = serde_json::to_string(&spawn).unwrap();
+        assert_eq!(serde_json::from_str::<Spawn>(&json).unwrap(), spawn);
+
+        // objNull
+        let input = r#"[["position",[0,0]],["direction",[0,0]],["side","civilian"],["object",<NULL-object>],["color",[0,0,0,0]]]"#;
+        let spawn: Spawn = from_str(input).unwrap();
+        assert_eq!(spawn.object, None);
+        assert_eq!(spawn.side, Side::Civilian);
+    }
+
+    #[test]
+    fn it_converts_with_arma_rs() {
+        assert_eq!(
+            Position::from_arma("[1,2,3]".into()).unwrap(),
+            Position::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(
+            Position::new(1.0, 2.5, 0.0).to_arma().to_string(),
+            "[1,2.5,0]"
+        );
+
+        assert_eq!(Side::from_arma("\"WEST\"".into()).unwrap(), Side::West);
+        assert_eq!(Side::AmbientLife.to_arma().to_string(), "\"AMBIENT LIFE\"");
+
+        assert_eq!(ObjectRef::new("2:345").to_arma().to_string(), "\"2:345\"");
+        assert_eq!(
+            ArmaColor::new(0.0, 0.0, 0.0, 0.5).to_arma().to_string(),
+            "[0,0,0,0.5]"
+        );
+    }
+
+    #[test]
+    fn it_reads_object_references() {
+        let unit = ObjectRef::new("B Alpha 1-1:1");
+        assert_eq!(unit.side(), Some(Side::West));
+        assert_eq!(unit.net_id(), None);
+
+        let object = ObjectRef::new("2:345");
+        assert_eq!(object.side(), None);
+        assert_eq!(object.net_id(), Some((2, 345)));
+    }
+
+    #[test]
+    fn it_rejects_invalid_game_values() {
+        let error = from_str::<Position>("[1]").unwrap_err();
+        assert!(error
+            .message
+            .starts_with("invalid length 1, expected an array of [x, y] or [x, y, z]"));
+
+        let error = from_str::<Position>("[1,2,3,4]").unwrap_err();
+        assert!(error.message.starts_with("invalid length 4"));
+
+        let error = from_str::<ArmaColor>("[1,2,0]").unwrap_err();
+        assert_eq!(
+            error.message,
+            "2 is not a valid color, each part must be from 0 to 1"
+        );
+
+        let error = from_str::<Side>("\"purple\"").unwrap_err();
+        assert_eq!(error.message, "\"purple\" is not a valid side");
+
+        assert_eq!(ArmaColor::new(1.0, 0.5, 0.0, 1.0).to_hex(), "#FF8000");
+    }
+}
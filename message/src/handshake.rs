@@ -0,0 +1,204 @@
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::{decrypt_bytes, encrypt_bytes};
+
+const SESSION_KEY_INFO: &[u8] = b"esm_message session key";
+
+// Authenticated with each packet, so nothing else encrypted with the server key is accepted as one
+const PACKET_PURPOSE: &[u8] = b"esm_message handshake packet";
+
+/*
+    Optional handshake that replaces the long-term server key with a per-connection session key.
+
+    Both sides create a Handshake, send the packet from `Handshake::packet` to the other side, and pass the
+    packet they received to `Handshake::complete`. The packets are encrypted with the long-term server key,
+    so only someone who has the key can take part. The ephemeral secrets are consumed once the session key
+    is derived, meaning a leaked server key cannot be used to decrypt traffic from previous connections.
+
+    Packet (as bytes, before encryption)
+    [
+        32 bytes -> The X25519 public key for this side of the connection
+    ]
+*/
+pub struct Handshake {
+    server_id: Vec<u8>,
+    secret: EphemeralSecret,
+    public_key: PublicKey,
+}
+
+impl Handshake {
+    pub fn new(server_id: &[u8]) -> Self {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public_key = PublicKey::from(&secret);
+
+        Handshake {
+            server_id: server_id.to_vec(),
+            secret,
+            public_key,
+        }
+    }
+
+    /// The packet to send to the other side of the connection
+    pub fn packet(&self, server_key: &[u8]) -> Result<Vec<u8>, String> {
+        encrypt_bytes(
+            &self.server_id,
+            self.public_key.as_bytes(),
+            server_key,
+            0,
+            PACKET_PURPOSE,
+        )
+    }
+
+    /// Consumes the handshake and derives the session key from the packet sent by the other side.
+    /// The returned key should be used in place of the server key for `Message::as_bytes` and `Message::from_bytes`
+    pub fn complete(self, packet: &[u8], server_key: &[u8]) -> Result<SessionKey, String> {
+        let (server_id, public_key) = match decrypt_bytes(packet, server_key, PACKET_PURPOSE) {
+            Ok(result) => result,
+            Err(e) => return Err(format!("Handshake failed. {e}")),
+        };
+
+        if server_id != self.server_id {
+            return Err(format!(
+                "Handshake failed. Expected server id {:?}, got {:?}",
+                String::from_utf8_lossy(&self.server_id),
+                String::from_utf8_lossy(&server_id)
+            ));
+        }
+
+        let public_key: [u8; 32] = match public_key.try_into() {
+            Ok(k) => k,
+            Err(k) => {
+                return Err(format!(
+                    "Handshake failed. Public key must be 32 bytes, got {}",
+                    k.len()
+                ))
+            }
+        };
+
+        // Our own packet being sent back to us
+        if public_key == *self.public_key.as_bytes() {
+            return Err("Handshake failed. Received our own public key".into());
+        }
+
+        let shared_secret = self.secret.diffie_hellman(&PublicKey::from(public_key));
+        if !shared_secret.was_contributory() {
+            return Err("Handshake failed. Public key is not contributory".into());
+        }
+
+        // Both sides need the same salt, regardless of who sent what
+        let mut public_keys = [*self.public_key.as_bytes(), public_key];
+        public_keys.sort();
+
+        let hkdf = Hkdf::<Sha256>::new(Some(&public_keys.concat()), shared_secret.as_bytes());
+
+        let mut session_key = [0_u8; 32];
+        let info = [SESSION_KEY_INFO, &self.server_id].concat();
+        if let Err(e) = hkdf.expand(&info, &mut session_key) {
+            return Err(format!("Handshake failed. Reason: {e}"));
+        }
+
+        Ok(SessionKey(session_key))
+    }
+}
+
+/// A 32 byte key that is only valid for the connection that performed the handshake
+#[derive(Clone, PartialEq, Eq)]
+pub struct SessionKey([u8; 32]);
+
+impl SessionKey {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SessionKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SessionKey(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::random_server_key;
+    use crate::{Data, Message};
+
+    #[test]
+    fn it_derives_the_same_session_key() {
+        let server_key = random_server_key();
+
+        let bot = Handshake::new(b"esm_testing");
+        let server = Handshake::new(b"esm_testing");
+
+        let bot_packet = bot.packet(&server_key).unwrap();
+        let server_packet = server.packet(&server_key).unwrap();
+
+        let bot_session = bot.complete(&server_packet, &server_key).unwrap();
+        let server_session = server.complete(&bot_packet, &server_key).unwrap();
+
+        assert_eq!(bot_session, server_session);
+        assert_ne!(bot_session.as_bytes(), &server_key[0..32]);
+
+        let message = Message::new()
+            .set_server_id(b"esm_testing")
+            .set_data(Data::Ping);
+
+        let bytes = message.as_bytes(bot_session.as_bytes()).unwrap();
+        assert!(Message::from_bytes(&bytes, &server_key).is_err());
+
+        let result = Message::from_bytes(&bytes, server_session.as_bytes()).unwrap();
+        assert_eq!(result.id, message.id);
+        assert_eq!(result.data, Data::Ping);
+    }
+
+    #[test]
+    fn it_rejects_packets_from_the_wrong_key() {
+        let bot = Handshake::new(b"esm_testing");
+        let server = Handshake::new(b"esm_testing");
+
+        let server_packet = server.packet(&random_server_key()).unwrap();
+        assert!(bot.complete(&server_packet, &random_server_key()).is_err());
+    }
+
+    #[test]
+    fn it_rejects_truncated_packets() {
+        let server_key = random_server_key();
+        let server_packet = Handshake::new(b"esm_testing").packet(&server_key).unwrap();
+
+        let error = Handshake::new(b"esm_testing")
+            .complete(&[], &server_key)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            "Handshake failed. Failed to decrypt. Packet is too short (0 bytes)"
+        );
+
+        // Cut off inside of the server id, the nonce and the encrypted key
+        for length in [1, 5, 13, 20, server_packet.len() - 1] {
+            let bot = Handshake::new(b"esm_testing");
+            assert!(bot.complete(&server_packet[..length], &server_key).is_err());
+        }
+
+        // Claims a server id longer than the packet
+        let bot = Handshake::new(b"esm_testing");
+        assert!(bot.complete(&[200, b'e', b's', b'm'], &server_key).is_err());
+    }
+
+    #[test]
+    fn it_rejects_its_own_packet() {
+        let server_key = random_server_key();
+        let bot = Handshake::new(b"esm_testing");
+
+        let bot_packet = bot.packet(&server_key).unwrap();
+        assert!(bot.complete(&bot_packet, &server_key).is_err());
+    }
+}
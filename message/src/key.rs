@@ -0,0 +1,114 @@
+use argon2::Argon2;
+
+use std::collections::HashSet;
+
+pub const KEY_SIZE: usize = 32;
+
+// The fewest unique bytes a key can contain before it is considered guessable.
+// A random 32 byte key will almost always have more than 25
+const MINIMUM_UNIQUE_BYTES: usize = 10;
+
+/// Derives a 32 byte encryption key from a passphrase using Argon2id.
+/// The salt must be unique per server (the server id works) and at least 8 bytes long
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_SIZE], String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".into());
+    }
+
+    let mut key = [0_u8; KEY_SIZE];
+    match Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key) {
+        Ok(_) => Ok(key),
+        Err(e) => Err(format!("Failed to derive key. Reason: {e}")),
+    }
+}
+
+/// Checks the part of the key used for encryption and rejects keys that are trivially guessable,
+/// such as a repeated character or a short repeating pattern.
+/// Keys provided as passphrases should be passed through derive_key instead
+pub fn validate_key_strength(server_key: &[u8]) -> Result<(), String> {
+    if server_key.len() < KEY_SIZE {
+        return Err("Server key must contain at least 32 bytes".into());
+    }
+
+    let key = &server_key[0..KEY_SIZE];
+
+    let unique_bytes = key.iter().collect::<HashSet<_>>().len();
+    if unique_bytes < MINIMUM_UNIQUE_BYTES {
+        return Err(format!(
+            "Server key is too weak. It must contain at least {MINIMUM_UNIQUE_BYTES} unique bytes, found {unique_bytes}"
+        ));
+    }
+
+    // Patterns like "abcdefghijkabcdefghijk..."
+    let repeats = (1..=(KEY_SIZE / 2)).find(|period| {
+        key.iter()
+            .enumerate()
+            .skip(*period)
+            .all(|(index, byte)| *byte == key[index - period])
+    });
+
+    if let Some(period) = repeats {
+        return Err(format!(
+            "Server key is too weak. It repeats every {period} bytes"
+        ));
+    }
+
+    // Runs like "0123456789:;<=>?@ABCDEF..."
+    let is_sequence = key
+        .windows(2)
+        .all(|pair| pair[1].wrapping_sub(pair[0]) == key[1].wrapping_sub(key[0]));
+
+    if is_sequence {
+        return Err("Server key is too weak. It is a sequence of bytes".into());
+    }
+
+    Ok(())
+}
+
+// A random key in the form the bot generates them, for tests that need one
+#[cfg(test)]
+pub(crate) fn random_server_key() -> Vec<u8> {
+    format!("{}-{}", uuid::Uuid::new_v4(), uuid::Uuid::new_v4()).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Message;
+
+    #[test]
+    fn it_derives_a_key_from_a_passphrase() {
+        let key = derive_key("password", b"esm_testing").unwrap();
+
+        assert_eq!(key, derive_key("password", b"esm_testing").unwrap());
+        assert_ne!(key, derive_key("password", b"esm_other_server").unwrap());
+        assert!(validate_key_strength(&key).is_ok());
+
+        let message = Message::new().set_server_id(b"esm_testing");
+        let bytes = message.as_bytes(&key).unwrap();
+        assert_eq!(Message::from_bytes(&bytes, &key).unwrap().id, message.id);
+
+        assert!(derive_key("", b"esm_testing").is_err());
+        assert!(derive_key("password", b"short").is_err());
+    }
+
+    #[test]
+    fn it_rejects_weak_keys() {
+        assert!(validate_key_strength(b"short").is_err());
+        assert!(validate_key_strength(&[b'a'; 32]).is_err());
+        assert!(validate_key_strength(b"abcdefghijklabcdefghijklabcdefghijkl").is_err());
+        assert!(validate_key_strength(b"0123456789:;<=>?@ABCDEFGHIJKLMNO").is_err());
+        assert!(validate_key_strength(b"password12password12password12pa").is_err());
+
+        let message = Message::new().set_server_id(b"esm_testing");
+        assert!(message.as_bytes(&[b'a'; 32]).is_err());
+    }
+
+    #[test]
+    fn it_accepts_strong_keys() {
+        assert!(validate_key_strength(&random_server_key()).is_ok());
+
+        let key: [u8; 32] = rand::random();
+        assert!(validate_key_strength(&key).is_ok());
+    }
+}
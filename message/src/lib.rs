@@ -1,22 +1,38 @@
+pub mod arma_time;
+pub mod challenge;
+pub mod chunk;
 pub mod data;
 pub mod error;
+pub mod game_value;
+pub mod handshake;
+pub mod key;
 pub mod metadata;
+pub mod number_string;
+mod padding;
 pub mod parser;
+pub mod revocation;
+pub mod sorted;
 
-use aes_gcm::aead::{Aead, NewAead};
+// Lets ImplIntoArma refer to this crate by name, the same way it does from any other crate
+extern crate self as esm_message;
+
+use aes_gcm::aead::{Aead, NewAead, Payload};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
-use arma_rs::FromArma;
+use arma_rs::{FromArma, IntoArma, Value as ArmaValue};
+use parser::{ParseError, ParseOptions, SerializeError};
 use rand::random;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub use challenge::*;
 pub use data::*;
 pub use error::*;
+pub use game_value::*;
+pub use handshake::*;
+pub use key::*;
 pub use metadata::*;
-
-// Numbers in Arma are best stored as Strings when sending across the wire to avoid precision loss.
-// Use this type for any numbers
-pub type NumberString = String;
+pub use number_string::*;
+pub use revocation::*;
 
 /*
     {
@@ -34,7 +50,7 @@ pub type NumberString = String;
         errors: []
     }
 */
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Message {
     pub id: Uuid,
 
@@ -127,12 +143,29 @@ impl Message {
         String::from_utf8_lossy(server_id).to_string()
     }
 
+    /// Decrypts a frame, refusing it if its server id or key is on the installed revocation list.
+    /// See RevocationList::install
     pub fn from_bytes(data: &[u8], key: &[u8]) -> Result<Message, String> {
-        decrypt_message(data, key)
+        decode_message(data, key, None).map_err(|e| e.to_string())
+    }
+
+    /// Same as from_bytes, but also refuses frames from any server id or key on this revocation list
+    pub fn from_bytes_checked(
+        data: &[u8],
+        key: &[u8],
+        revocations: &RevocationList,
+    ) -> Result<Message, DecodeError> {
+        decode_message(data, key, Some(revocations))
     }
 
     pub fn as_bytes(&self, key: &[u8]) -> Result<Vec<u8>, String> {
-        encrypt_message(self, key)
+        encrypt_message(self, key, false)
+    }
+
+    /// Same as as_bytes, but pads the message before encrypting so its length does not reveal the data type.
+    /// from_bytes removes the padding automatically
+    pub fn as_padded_bytes(&self, key: &[u8]) -> Result<Vec<u8>, String> {
+        encrypt_message(self, key, true)
     }
 
     //  [
@@ -191,6 +224,84 @@ impl Message {
 
         Ok(message)
     }
+
+    /// Same as from_arma, but reads all five parts from the one array they are documented as.
+    /// This is the reverse of to_arma: from_arma_str(&message.to_arma().to_string()) gives back the same message,
+    /// apart from the server id, which is not part of the array
+    pub fn from_arma_str(input: &str) -> Result<Message, String> {
+        let error = |e: ParseError| {
+            format!("[esm_message::Message::from_arma_str] Failed to parse input. {e}")
+        };
+
+        let elements = parser::split_elements(input, &ParseOptions::default()).map_err(error)?;
+        let [id, message_type, data, metadata, errors] = elements[..] else {
+            return Err(format!(
+                "[esm_message::Message::from_arma_str] Expected 5 elements, got {}",
+                elements.len()
+            ));
+        };
+
+        let id: String = parser::from_str(id).map_err(error)?;
+        let message_type: String = parser::from_str(message_type).map_err(error)?;
+
+        Self::from_arma(
+            id,
+            message_type,
+            data.to_string(),
+            metadata.to_string(),
+            errors.to_string(),
+        )
+    }
+
+    /// Same as from_arma_str, for the array arma-rs has already parsed, so none of it is converted back to text
+    pub fn from_arma_value(input: &ArmaValue) -> Result<Message, String> {
+        let ArmaValue::Array(elements) = input else {
+            return Err(format!(
+                "[esm_message::Message::from_arma_value] Expected an array, got {input}"
+            ));
+        };
+
+        let [id, message_type, data, metadata, errors] = &elements[..] else {
+            return Err(format!(
+                "[esm_message::Message::from_arma_value] Expected 5 elements, got {}",
+                elements.len()
+            ));
+        };
+
+        let error = |e: ParseError| {
+            format!("[esm_message::Message::from_arma_value] Failed to convert input. {e}")
+        };
+
+        Ok(Message {
+            id: parser::from_value(id).map_err(error)?,
+            message_type: parser::from_value(message_type).map_err(error)?,
+            server_id: None,
+            data: Data::from_arma_value(data)?,
+            metadata: Metadata::from_arma_value(metadata)?,
+            errors: Error::from_arma_value(errors)?,
+        })
+    }
+}
+
+impl Message {
+    /// Writes the array from_arma documents. Data, metadata and errors keep their type and content
+    pub fn try_to_arma(&self) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Array(vec![
+            ArmaValue::String(self.id.to_string()),
+            parser::to_arma_value(&self.message_type)?,
+            self.data.to_arma_tagged()?,
+            self.metadata.to_arma_tagged()?,
+            parser::to_arma_value(&self.errors)?,
+        ]))
+    }
+}
+
+impl IntoArma for Message {
+    fn to_arma(&self) -> ArmaValue {
+        // The serializer only fails when a Serialize implementation returns an error
+        self.try_to_arma()
+            .unwrap_or_else(|e| panic!("Message could not be written as an Arma value. {e}"))
+    }
 }
 
 impl std::fmt::Display for Message {
@@ -239,12 +350,84 @@ pub enum Type {
 
 ////////////////////////////////////////////////////////////
 
+// Frame flags
+pub(crate) const FLAG_PADDED: u8 = 0b0000_0001;
+
+// Authenticated with the encrypted bytes of messages. Empty, so message frames stay readable by older peers.
+// Anything else sent with encrypt_bytes has its own purpose, so it can never be passed off as a message or each other
+const MESSAGE_PURPOSE: &[u8] = b"";
+
+// Set on the nonce size byte when a flags byte follows the nonce. Frames without any flags keep the original
+// layout, so peers that predate flags can still read them
+const NONCE_SIZE_HAS_FLAGS: u8 = 0b1000_0000;
+
 #[allow(clippy::ptr_arg)]
-fn encrypt_message(message: &Message, server_key: &[u8]) -> Result<Vec<u8>, String> {
-    if server_key.len() < 32 {
-        return Err("Server key must contain at least 32 bytes".into());
+fn encrypt_message(message: &Message, server_key: &[u8], padded: bool) -> Result<Vec<u8>, String> {
+    // Serialize this message
+    let message_bytes = match serde_json::to_vec(&message) {
+        Ok(bytes) => bytes,
+        Err(e) => return Err(e.to_string()),
+    };
+
+    let server_id = message.server_id.clone().unwrap();
+    let flags = if padded { FLAG_PADDED } else { 0 };
+    encrypt_bytes(
+        &server_id,
+        &message_bytes,
+        server_key,
+        flags,
+        MESSAGE_PURPOSE,
+    )
+}
+
+// The installed revocation list is always checked, so no caller can skip it
+fn decode_message(
+    bytes: &[u8],
+    server_key: &[u8],
+    revocations: Option<&RevocationList>,
+) -> Result<Message, DecodeError> {
+    if let Some(installed) = RevocationList::installed() {
+        installed.check(bytes, server_key)?;
     }
 
+    if let Some(revocations) = revocations {
+        revocations.check(bytes, server_key)?;
+    }
+
+    decrypt_message(bytes, server_key).map_err(DecodeError::Failed)
+}
+
+fn decrypt_message(bytes: &[u8], server_key: &[u8]) -> Result<Message, String> {
+    let (server_id, decrypted_bytes) = decrypt_bytes(bytes, server_key, MESSAGE_PURPOSE)?;
+
+    // And deserialize into a struct
+    let mut message: Message = match serde_json::from_slice(&decrypted_bytes) {
+        Ok(message) => message,
+        Err(e) => {
+            return Err(format!(
+                "Failed to deserialize. Reason: {:?}. Message: {:#?}",
+                e,
+                String::from_utf8(decrypted_bytes.clone())
+                    .unwrap_or(format!("Bytes: {:?}", decrypted_bytes))
+            ))
+        }
+    };
+
+    // Store the server id
+    message.server_id = Some(server_id);
+
+    Ok(message)
+}
+
+pub(crate) fn encrypt_bytes(
+    server_id: &[u8],
+    bytes: &[u8],
+    server_key: &[u8],
+    flags: u8,
+    purpose: &[u8],
+) -> Result<Vec<u8>, String> {
+    validate_key_strength(server_key)?;
+
     // Setup everything for encryption
     let encryption_key = Key::from_slice(&server_key[0..32]); // server_key has to be exactly 32 bytes
     let encryption_cipher = Aes256Gcm::new(encryption_key);
@@ -256,91 +439,137 @@ fn encrypt_message(message: &Message, server_key: &[u8]) -> Result<Vec<u8>, Stri
         [
             1 byte -> Size of server id (server_id_bytes)
             # of server_id_bytes -> The server id
-            1 byte -> Size of Nonce (nonce_bytes)
+            1 byte -> Size of Nonce (nonce_bytes). The high bit is set when the flags byte is present
             # of nonce_bytes -> The nonce
+            1 byte, only when there are flags -> Flags. Authenticated along with the encrypted json
             rest -> The encrypted json
         ]
+
+        The purpose and the flags are authenticated along with the encrypted bytes, but are not sent
     */
     // Start the packet off with the id length and itself
-    let server_id = message.server_id.clone().unwrap();
     let mut packet: Vec<u8> = vec![server_id.len() as u8];
-    packet.extend(&*server_id);
+    packet.extend(server_id);
 
     // Append the nonce length and itself to the packet
-    packet.push(nonce_key.len() as u8);
-    packet.extend(&*nonce_key);
+    if flags == 0 {
+        packet.push(nonce_key.len() as u8);
+        packet.extend(&*nonce_key);
+    } else {
+        packet.push(nonce_key.len() as u8 | NONCE_SIZE_HAS_FLAGS);
+        packet.extend(&*nonce_key);
+        packet.push(flags);
+    }
 
-    // Serialize this message
-    let message_bytes = match serde_json::to_vec(&message) {
+    let bytes = if flags & FLAG_PADDED != 0 {
+        padding::pad(bytes)
+    } else {
+        bytes.to_vec()
+    };
+
+    // Encrypt the bytes
+    let mut aad = purpose.to_vec();
+    if flags != 0 {
+        aad.push(flags);
+    }
+
+    let payload = Payload {
+        msg: &bytes,
+        aad: &aad,
+    };
+
+    let encrypted_bytes = match encryption_cipher.encrypt(encryption_nonce, payload) {
         Ok(bytes) => bytes,
         Err(e) => return Err(e.to_string()),
     };
 
-    // Encrypt the message
-    let encrypted_message =
-        match encryption_cipher.encrypt(encryption_nonce, message_bytes.as_ref()) {
-            Ok(bytes) => bytes,
-            Err(e) => return Err(e.to_string()),
-        };
-
-    // Now add the encrypted message to the end. This completes the packet
-    packet.extend(&*encrypted_message);
+    // Now add the encrypted bytes to the end. This completes the packet
+    packet.extend(&*encrypted_bytes);
 
     Ok(packet)
 }
 
-fn decrypt_message(bytes: &[u8], server_key: &[u8]) -> Result<Message, String> {
+/// Returns the server id and the decrypted bytes contained in the packet.
+/// The purpose has to be the same one the packet was encrypted with
+pub(crate) fn decrypt_bytes(
+    bytes: &[u8],
+    server_key: &[u8],
+    purpose: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), String> {
     if server_key.len() < 32 {
         return Err("Server key must contain at least 32 bytes".into());
     }
 
+    let too_short = || {
+        format!(
+            "Failed to decrypt. Packet is too short ({} bytes)",
+            bytes.len()
+        )
+    };
+
     // The first byte is the length of the server_id so we know how many bytes to extract
-    let id_length = bytes[0] as usize;
+    let id_length = *bytes.first().ok_or_else(too_short)? as usize;
 
     // Extract the server ID and convert to a vec
-    let server_id = bytes[1..=id_length].to_vec();
+    let server_id = bytes.get(1..=id_length).ok_or_else(too_short)?.to_vec();
 
     // Now to decrypt. First step, extract the nonce
     let nonce_offset = 1 + id_length;
-    let nonce_size = bytes[nonce_offset] as usize;
+    let nonce_size = *bytes.get(nonce_offset).ok_or_else(too_short)?;
+    let has_flags = nonce_size & NONCE_SIZE_HAS_FLAGS != 0;
+    let nonce_size = (nonce_size & !NONCE_SIZE_HAS_FLAGS) as usize;
+    if nonce_size != 12 {
+        return Err(format!(
+            "Failed to decrypt. Nonce must be 12 bytes, got {nonce_size}"
+        ));
+    }
+
     let nonce_offset = 1 + nonce_offset;
-    let nonce = bytes[nonce_offset..(nonce_offset + nonce_size)].to_vec();
-    let nonce = Nonce::from_slice(&nonce);
+    let nonce = bytes
+        .get(nonce_offset..(nonce_offset + nonce_size))
+        .ok_or_else(too_short)?;
+    let nonce = Nonce::from_slice(nonce);
+
+    // Then the flags, if there are any
+    let mut encrypted_offset = nonce_offset + nonce_size;
+    let flags = if has_flags {
+        let flags = *bytes.get(encrypted_offset).ok_or_else(too_short)?;
+        encrypted_offset += 1;
+        Some(flags)
+    } else {
+        None
+    };
 
     // Next, extract the encrypted bytes
-    let enc_offset = nonce_offset + nonce_size;
-    let encrypted_bytes = bytes[enc_offset..].to_vec();
+    let encrypted_bytes = &bytes[encrypted_offset..];
 
     // Build the cipher
     let server_key = &server_key[0..=31]; // server_key has to be exactly 32 bytes
     let key = Key::from_slice(server_key);
     let cipher = Aes256Gcm::new(key);
 
-    // Decrypt! This also ensures the message has been encrypted using this server's key.
-    let decrypted_bytes = match cipher.decrypt(nonce, encrypted_bytes.as_ref()) {
-        Ok(message) => message,
-        Err(e) => {
-            return Err(format!("Failed to decrypt. Reason: {}", e));
-        }
+    // Decrypt! This also ensures the bytes have been encrypted using this server's key.
+    let mut aad = purpose.to_vec();
+    aad.extend(flags);
+
+    let payload = Payload {
+        msg: encrypted_bytes,
+        aad: &aad,
     };
 
-    // And deserialize into a struct
-    let mut message: Message = match serde_json::from_slice(&decrypted_bytes) {
-        Ok(message) => message,
-        Err(e) => {
-            return Err(format!(
-                "Failed to deserialize. Reason: {:?}. Message: {:#?}",
-                e,
-                String::from_utf8(decrypted_bytes.clone())
-                    .unwrap_or(format!("Bytes: {:?}", decrypted_bytes))
-            ))
-        }
+    let decrypted_bytes = match cipher.decrypt(nonce, payload) {
+        Ok(bytes) => bytes,
+        Err(e) => return Err(format!("Failed to decrypt. Reason: {}", e)),
     };
 
-    // Store the server id
-    message.server_id = Some(server_id);
+    if flags.unwrap_or_default() & FLAG_PADDED == 0 {
+        return Ok((server_id, decrypted_bytes));
+    }
 
-    Ok(message)
+    match padding::unpad(decrypted_bytes) {
+        Ok(bytes) => Ok((server_id, bytes)),
+        Err(e) => Err(format!("Failed to decrypt. {e}")),
+    }
 }
 
 #[cfg(test)]
@@ -354,8 +583,8 @@ mod tests {
 
         let server_init = Init {
             server_name: "server_name".into(),
-            price_per_object: "10".into(),
-            territory_lifetime: "7".into(),
+            price_per_object: 10.into(),
+            territory_lifetime: 7.into(),
             territory_data: "[]".into(),
             server_start_time: chrono::Utc::now(),
             extension_version: "2.0.0".into(),
@@ -378,7 +607,7 @@ mod tests {
         );
         let server_key = server_key.as_bytes();
 
-        let encrypted_bytes = encrypt_message(&message, server_key);
+        let encrypted_bytes = encrypt_message(&message, server_key, false);
         assert!(encrypted_bytes.is_ok());
 
         let decrypted_message = decrypt_message(&encrypted_bytes.unwrap(), server_key);
@@ -434,8 +663,8 @@ mod tests {
 
         let server_init = Init {
             server_name: "server_name".into(),
-            price_per_object: "10".into(),
-            territory_lifetime: "7".into(),
+            price_per_object: 10.into(),
+            territory_lifetime: 7.into(),
             territory_data: "[]".into(),
             server_start_time: chrono::Utc::now(),
             extension_version: "2.0.0".into(),
@@ -496,7 +725,7 @@ mod tests {
                 foo: "test\"ing".into(),
             }))
             .set_metadata(Metadata::Test(metadata::Test {
-                foo: "\"testing\" \\(* \\\\\" *)/ - \"nested\"".into(),
+                foo: "\"testing\" \\(* \"\"\" *)/ - \"nested\"".into(),
             }))
             .add_error(ErrorType::Message, "This is a message")
             .add_error(ErrorType::Code, "CODING");
@@ -518,4 +747,260 @@ mod tests {
         assert_eq!(result.metadata, expectation.metadata);
         assert_eq!(result.errors, expectation.errors);
     }
+
+    #[test]
+    fn it_converts_to_arma_and_back() {
+        let id = Uuid::new_v4();
+        let message = Message::new()
+            .set_id(id)
+            .set_type(Type::Test)
+            .set_data(Data::Test(data::Test {
+                foo: "test\"ing".into(),
+            }))
+            .set_metadata(Metadata::Test(metadata::Test {
+                foo: "\"nested\"".into(),
+            }))
+            .add_error_code("CODING")
+            .add_error_message("This is a message");
+
+        let arma = message.to_arma().to_string();
+        assert_eq!(
+            arma,
+            format!(
+                r#"["{id}","test",[["type","test"],["content",[["foo","test""ing"]]]],[["type","test"],["content",[["foo","""nested"""]]]],[[["type","code"],["content","CODING"]],[["type","message"],["content","This is a message"]]]]"#
+            )
+        );
+        assert_eq!(Message::from_arma_str(&arma).unwrap(), message);
+
+        // Empty data and metadata keep their type, rather than becoming nil
+        let message = Message::new().set_id(id);
+        let arma = message.to_arma().to_string();
+        assert_eq!(
+            arma,
+            format!(r#"["{id}","event",[["type","empty"]],[["type","empty"]],[]]"#)
+        );
+        assert_eq!(Message::from_arma_str(&arma).unwrap(), message);
+
+        let init = Init {
+            server_name: "server_name".into(),
+            price_per_object: 10.into(),
+            territory_lifetime: 7.into(),
+            territory_data: "[]".into(),
+            server_start_time: chrono::Utc::now(),
+            extension_version: "2.0.0".into(),
+            vg_enabled: false,
+            vg_max_sizes: String::new(),
+        };
+
+        let message = Message::new().set_data(Data::Init(init));
+        let arma = message.to_arma().to_string();
+        assert_eq!(Message::from_arma_str(&arma).unwrap(), message);
+    }
+
+    #[test]
+    fn it_reads_formatted_messages() {
+        let id = Uuid::new_v4();
+        let input = format!(
+            r#"
+            // Fixture written by hand
+            [
+                "{id}",
+                "test",
+                [ ["type", "test"], ["content", [ ["foo", "bar"] ]] ],
+                [ ["type", "empty"] ],
+                [] /* no errors */
+            ]
+            "#
+        );
+
+        let message = Message::from_arma_str(&input).unwrap();
+        assert_eq!(
+            message,
+            Message::new()
+                .set_id(id)
+                .set_type(Type::Test)
+                .set_data(Data::Test(data::Test { foo: "bar".into() }))
+        );
+    }
+
+    #[test]
+    fn it_rejects_arrays_that_are_not_messages() {
+        let error = Message::from_arma_str(r#"["id","test"]"#).unwrap_err();
+        assert_eq!(
+            error,
+            "[esm_message::Message::from_arma_str] Expected 5 elements, got 2"
+        );
+
+        let error = Message::from_arma_str(r#"[["id"],"test",[],[],[]]"#).unwrap_err();
+        assert!(error.starts_with("[esm_message::Message::from_arma_str] Failed to parse input."));
+    }
+
+    #[test]
+    fn it_converts_from_arma_values() {
+        let message = Message::new()
+            .set_type(Type::Test)
+            .set_data(Data::Test(data::Test {
+                foo: "test\"ing".into(),
+            }))
+            .set_metadata(Metadata::Test(metadata::Test { foo: "bar".into() }))
+            .add_error_code("CODING");
+
+        assert_eq!(
+            Message::from_arma_value(&message.to_arma()).unwrap(),
+            message
+        );
+
+        let error = Message::from_arma_value(&ArmaValue::Array(vec![])).unwrap_err();
+        assert_eq!(
+            error,
+            "[esm_message::Message::from_arma_value] Expected 5 elements, got 0"
+        );
+
+        let mut value = message.to_arma();
+        if let ArmaValue::Array(elements) = &mut value {
+            elements[1] = ArmaValue::String("nope".into());
+        }
+
+        let error = Message::from_arma_value(&value).unwrap_err();
+        assert!(error.starts_with(
+            "[esm_message::Message::from_arma_value] Failed to convert input. unknown variant `nope`"
+        ));
+    }
+
+    fn every_data() -> Vec<Data> {
+        let post_init = data::PostInit {
+            build_number: "1234".into(),
+            community_id: "esm".into(),
+            extdb_path: "C:\\extdb".into(),
+            extdb_version: 3,
+            gambling_modifier: 1.into(),
+            gambling_payout_base: 95.into(),
+            gambling_payout_randomizer_max: "1.5".parse().unwrap(),
+            gambling_payout_randomizer_mid: "0.5".parse().unwrap(),
+            gambling_payout_randomizer_min: 0.into(),
+            gambling_win_percentage: 35.into(),
+            logging_add_player_to_territory: true,
+            logging_demote_player: false,
+            logging_exec: true,
+            logging_gamble: false,
+            logging_modify_player: true,
+            logging_pay_territory: false,
+            logging_promote_player: true,
+            logging_remove_player_from_territory: false,
+            logging_reward_player: true,
+            logging_transfer_poptabs: false,
+            logging_upgrade_territory: true,
+            logging_channel_id: "1234567890".into(),
+            server_id: "esm_malden".into(),
+            taxes_territory_payment: 10.into(),
+            taxes_territory_upgrade: 25.into(),
+            territory_admin_uids: vec!["76561198000000000".into(), "76561198000000001".into()],
+            version: "2.0.0".into(),
+        };
+
+        vec![
+            Data::Empty,
+            Data::Ping,
+            Data::Pong,
+            Data::Test(data::Test {
+                foo: "\"quoted\" and ünïcödé".into(),
+            }),
+            Data::Init(Init {
+                extension_version: "2.0.0".into(),
+                price_per_object: 10.into(),
+                server_name: "My \"Exile\" server".into(),
+                server_start_time: chrono::Utc::now(),
+                territory_data: "[[\"level\",1]]".into(),
+                territory_lifetime: 7.into(),
+                vg_enabled: true,
+                vg_max_sizes: "[]".into(),
+            }),
+            Data::PostInit(Box::new(post_init)),
+            Data::Query(data::Query {
+                arguments: std::collections::HashMap::from([
+                    ("uid".into(), "76561198000000000".into()),
+                    ("territory_id".into(), "abc".into()),
+                ]),
+                name: "territories".into(),
+            }),
+            Data::QueryResult(data::QueryResult {
+                results: vec![r#"{"id":1}"#.into(), String::new()],
+            }),
+            Data::SendToChannel(data::SendToChannel {
+                id: "1234567890".into(),
+                content: "Hello [world]".into(),
+            }),
+            Data::Reward(data::Reward {
+                items: Some(std::collections::HashMap::from([
+                    ("Exile_Item_Beer".into(), 2.into()),
+                    ("Exile_Item_Water".into(), 1.into()),
+                ])),
+                locker_poptabs: Some(1500.into()),
+                player_poptabs: None,
+                respect: Some("-0.25".parse().unwrap()),
+                vehicles: Some(vec![std::collections::HashMap::from([
+                    ("class_name".into(), "Exile_Car_Offroad".into()),
+                    ("spawn_location".into(), "player_decides".into()),
+                ])]),
+            }),
+            Data::Sqf(data::Sqf {
+                execute_on: "server".into(),
+                code: "private _a = \"b\"; [_a, 1]".into(),
+            }),
+            Data::SqfResult(data::SqfResult { result: None }),
+            Data::SqfResult(data::SqfResult {
+                result: Some("[1,2]".into()),
+            }),
+        ]
+    }
+
+    fn every_metadata() -> Vec<Metadata> {
+        let player = metadata::Player {
+            discord_id: Some("1234567890".into()),
+            discord_mention: Some("<@1234567890>".into()),
+            discord_name: Some("\"Bryan\"".into()),
+            steam_uid: "76561198000000000".into(),
+        };
+
+        vec![
+            Metadata::Empty,
+            Metadata::Test(metadata::Test { foo: "bar".into() }),
+            Metadata::Command(metadata::Command {
+                player: player.clone(),
+                target: None,
+            }),
+            Metadata::Command(metadata::Command {
+                player: player.clone(),
+                target: Some(metadata::Player {
+                    discord_id: None,
+                    discord_mention: None,
+                    discord_name: None,
+                    ..player
+                }),
+            }),
+        ]
+    }
+
+    #[test]
+    fn it_converts_every_data_and_metadata_variant_to_arma_and_back() {
+        for data in every_data() {
+            let arma = data.to_arma_tagged().unwrap();
+            assert_eq!(Data::from_arma_value(&arma).unwrap(), data, "{arma}");
+            assert_eq!(Data::from_arma(arma.to_string()).unwrap(), data, "{arma}");
+        }
+
+        for metadata in every_metadata() {
+            let arma = metadata.to_arma_tagged().unwrap();
+            assert_eq!(
+                Metadata::from_arma_value(&arma).unwrap(),
+                metadata,
+                "{arma}"
+            );
+            assert_eq!(
+                Metadata::from_arma(arma.to_string()).unwrap(),
+                metadata,
+                "{arma}"
+            );
+        }
+    }
 }
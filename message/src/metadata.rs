@@ -1,3 +1,4 @@
+use crate::parser::SerializeError;
 use arma_rs::{FromArma, IntoArma, Value as ArmaValue};
 use message_proc::ImplIntoArma;
 use serde::{Deserialize, Serialize};
@@ -26,6 +27,19 @@ impl IntoArma for Metadata {
     }
 }
 
+impl Metadata {
+    /// Same as to_arma, but keeps the type the metadata is: [["type", "command"], ["content", [...]]].
+    /// This is the form FromArma reads, so from_arma(metadata.to_arma_tagged()?.to_string()) gives back the same metadata
+    pub fn to_arma_tagged(&self) -> Result<ArmaValue, SerializeError> {
+        crate::parser::to_arma_value(self)
+    }
+
+    /// Reads the [["type", ...], ["content", ...]] form of metadata from the arma_rs::Value an extension call received
+    pub fn from_arma_value(input: &ArmaValue) -> Result<Self, String> {
+        crate::parser::Parser::from_value(input)
+    }
+}
+
 impl FromArma for Metadata {
     fn from_arma(input: String) -> Result<Self, String> {
         crate::parser::Parser::from_arma(&input)
@@ -87,6 +101,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_keeps_the_type_in_tagged_output() {
+        assert_eq!(
+            Metadata::Empty.to_arma_tagged().unwrap().to_string(),
+            r#"[["type","empty"]]"#
+        );
+    }
+
     #[test]
     fn it_converts_to_arma() {
         let command = Command {
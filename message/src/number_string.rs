@@ -0,0 +1,359 @@
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::de::{self, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// The name NumberString deserializes itself with, so the Arma parser can check its text for lost precision
+pub(crate) const NUMBER_STRING: &str = "$esm_message::NumberString";
+
+// Far past anything Arma or an f64 can produce, but keeps "1e999999999" from becoming a billion zeros
+const MAX_EXPONENT: i64 = 400;
+
+/// Numbers in Arma are best stored as Strings when sending across the wire to avoid precision loss.
+/// Use this type for any numbers.
+///
+/// Deserializes from numbers, numeric strings and scientific notation, and normalizes them to plain decimal text:
+/// 1500, "1500", 1.5e3 and "1.5e+003" all become "1500".
+/// parse and TryFrom follow the same rules, so text that is not a number, including "", is an error
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NumberString(String);
+
+// An empty string is not a number, so it could not be read back
+impl Default for NumberString {
+    fn default() -> Self {
+        NumberString::from(0)
+    }
+}
+
+impl NumberString {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    pub fn to_i64(&self) -> Result<i64, String> {
+        self.0
+            .parse()
+            .map_err(|e| format!("{:?} is not a valid i64. Reason: {e}", self.0))
+    }
+
+    pub fn to_u64(&self) -> Result<u64, String> {
+        self.0
+            .parse()
+            .map_err(|e| format!("{:?} is not a valid u64. Reason: {e}", self.0))
+    }
+
+    /// Lossy for numbers with more than 15 or so significant digits
+    pub fn to_f64(&self) -> Result<f64, String> {
+        self.0
+            .parse()
+            .map_err(|e| format!("{:?} is not a valid f64. Reason: {e}", self.0))
+    }
+}
+
+/*
+    Rewrites a decimal number, with or without an exponent, as plain decimal text. Returns None if it is not a number.
+    Works on the digits themselves, so nothing is lost to floating point: "1.23456789012e+011" -> "123456789012"
+*/
+fn normalize(input: &str) -> Option<String> {
+    let input = input.trim();
+
+    let (negative, unsigned) = match input.as_bytes().first()? {
+        b'-' => (true, &input[1..]),
+        b'+' => (false, &input[1..]),
+        _ => (false, input),
+    };
+
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(index) => (
+            &unsigned[..index],
+            unsigned[index + 1..].parse::<i64>().ok()?,
+        ),
+        None => (unsigned, 0),
+    };
+
+    if exponent.abs() > MAX_EXPONENT {
+        return None;
+    }
+
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if (integer.is_empty() && fraction.is_empty())
+        || !integer.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // Where the decimal point goes, counted from the start of the digits
+    let mut point = integer.len() as i64 + exponent;
+
+    let digits = format!("{integer}{fraction}");
+    let significant = digits.trim_start_matches('0');
+    point -= (digits.len() - significant.len()) as i64;
+
+    let significant = significant.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some("0".into());
+    }
+
+    let mut output = String::new();
+    if negative {
+        output.push('-');
+    }
+
+    let length = significant.len() as i64;
+    if point <= 0 {
+        output.push_str("0.");
+        output.push_str(&"0".repeat(-point as usize));
+        output.push_str(significant);
+    } else if point >= length {
+        output.push_str(significant);
+        output.push_str(&"0".repeat((point - length) as usize));
+    } else {
+        output.push_str(&significant[..point as usize]);
+        output.push('.');
+        output.push_str(&significant[point as usize..]);
+    }
+
+    Some(output)
+}
+
+impl FromStr for NumberString {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match normalize(input) {
+            Some(n) => Ok(NumberString(n)),
+            None => Err(format!("{input:?} is not a number")),
+        }
+    }
+}
+
+impl TryFrom<String> for NumberString {
+    type Error = String;
+
+    fn try_from(input: String) -> Result<Self, Self::Error> {
+        input.parse()
+    }
+}
+
+impl TryFrom<&str> for NumberString {
+    type Error = String;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        input.parse()
+    }
+}
+
+macro_rules! from_integer {
+    ($($integer:ty),*) => {
+        $(
+            impl From<$integer> for NumberString {
+                fn from(input: $integer) -> Self {
+                    NumberString(input.to_string())
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl From<NumberString> for String {
+    fn from(input: NumberString) -> Self {
+        input.0
+    }
+}
+
+impl Deref for NumberString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for NumberString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for NumberString {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for NumberString {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for NumberString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for NumberString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for NumberString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NUMBER_STRING, NumberStringVisitor)
+    }
+}
+
+struct NumberStringVisitor;
+
+impl<'de> Visitor<'de> for NumberStringVisitor {
+    type Value = NumberString;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number or a numeric string")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<NumberString, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<NumberString, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i128<E: de::Error>(self, value: i128) -> Result<NumberString, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<NumberString, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u128<E: de::Error>(self, value: u128) -> Result<NumberString, E> {
+        Ok(value.into())
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<NumberString, E> {
+        if !value.is_finite() {
+            return Err(E::invalid_value(Unexpected::Float(value), &self));
+        }
+
+        // Display never uses an exponent, so this only tidies up "-0" and trailing zeros
+        value
+            .to_string()
+            .parse()
+            .map_err(|_| E::invalid_value(Unexpected::Float(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<NumberString, E> {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(Unexpected::Str(value), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::from_str;
+    use crate::Reward;
+
+    #[test]
+    fn it_normalizes_numbers() {
+        for (input, expected) in [
+            ("1500", "1500"),
+            ("  +1500 ", "1500"),
+            ("001500.000", "1500"),
+            ("1.5e3", "1500"),
+            ("1.5e+003", "1500"),
+            ("1.23456789012e+011", "123456789012"),
+            ("-2.5E-3", "-0.0025"),
+            (".5", "0.5"),
+            ("5.", "5"),
+            ("-0.0", "0"),
+            ("0e10", "0"),
+        ] {
+            assert_eq!(input.parse::<NumberString>().unwrap(), expected, "{input}");
+        }
+
+        for input in [
+            "", "-", ".", "e5", "1e", "1.2.3", "0x10", "nan", "inf", "1e9999",
+        ] {
+            assert!(input.parse::<NumberString>().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn it_deserializes_numbers_and_numeric_strings() {
+        let input = r#"[["items",[["a",1],["b","2"],["c",3.55e2]]],["locker_poptabs",1500],["player_poptabs","1.5e+001"],["respect",-0.25]]"#;
+        let reward: Reward = from_str(input).unwrap();
+
+        let items = reward.items.unwrap();
+        assert_eq!(items["a"], "1");
+        assert_eq!(items["b"], "2");
+        assert_eq!(items["c"], "355");
+        assert_eq!(reward.locker_poptabs.unwrap(), "1500");
+        assert_eq!(reward.player_poptabs.unwrap(), "15");
+        assert_eq!(reward.respect.unwrap(), "-0.25");
+
+        let error = from_str::<Reward>(r#"[["respect","lots"]]"#).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid value: string \"lots\", expected a number or a numeric string"
+        );
+
+        let reward: Reward =
+            serde_json::from_str(r#"{"respect": 1500, "locker_poptabs": "15e2"}"#).unwrap();
+        assert_eq!(reward.respect.unwrap(), "1500");
+        assert_eq!(reward.locker_poptabs.unwrap(), "1500");
+    }
+
+    #[test]
+    fn it_converts_to_numbers() {
+        let number: NumberString = "1.5e+003".parse().unwrap();
+        assert_eq!(number.to_i64(), Ok(1500));
+        assert_eq!(number.to_u64(), Ok(1500));
+        assert_eq!(number.to_f64(), Ok(1500.0));
+        assert_eq!(serde_json::to_string(&number).unwrap(), "\"1500\"");
+
+        let number = NumberString::from(-2);
+        assert!(number.to_u64().is_err());
+        assert_eq!(number.to_i64(), Ok(-2));
+
+        let number: NumberString = "0.5".parse().unwrap();
+        assert_eq!(
+            number.to_i64().unwrap_err(),
+            "\"0.5\" is not a valid i64. Reason: invalid digit found in string"
+        );
+        assert_eq!(number.to_f64(), Ok(0.5));
+    }
+
+    #[test]
+    fn it_follows_the_same_rules_when_converting_text() {
+        assert_eq!(NumberString::try_from("1.5e+003").unwrap(), "1500");
+        assert_eq!(NumberString::try_from(String::from(" 25 ")).unwrap(), "25");
+        assert!(NumberString::try_from("").is_err());
+        assert!(NumberString::try_from("value_1").is_err());
+
+        // The default has to be readable as well
+        let number = NumberString::default();
+        assert_eq!(number, "0");
+        assert_eq!(
+            from_str::<NumberString>(&format!("\"{number}\"")).unwrap(),
+            number
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// Padding used to hide the length of a message before it is encrypted.
+// The padded bytes end with 0x80 followed by zeros (ISO/IEC 7816-4) so the padding can always be removed,
+// even if the message itself ends with zeros.
+
+// Messages are padded to the next power of two, starting at this size
+const MINIMUM_BUCKET_SIZE: usize = 256;
+
+// Past this size, messages are padded to the next multiple of it instead
+const MAXIMUM_BUCKET_SIZE: usize = 64 * 1024;
+
+const PADDING_MARKER: u8 = 0x80;
+
+pub(crate) fn pad(bytes: &[u8]) -> Vec<u8> {
+    let size = bucket_size(bytes.len() + 1);
+
+    let mut padded = Vec::with_capacity(size);
+    padded.extend(bytes);
+    padded.push(PADDING_MARKER);
+    padded.resize(size, 0);
+    padded
+}
+
+pub(crate) fn unpad(mut bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    let Some(marker_index) = bytes.iter().rposition(|b| *b != 0) else {
+        return Err("Padding marker is missing".into());
+    };
+
+    if bytes[marker_index] != PADDING_MARKER {
+        return Err("Padding marker is missing".into());
+    }
+
+    bytes.truncate(marker_index);
+    Ok(bytes)
+}
+
+fn bucket_size(length: usize) -> usize {
+    if length <= MINIMUM_BUCKET_SIZE {
+        return MINIMUM_BUCKET_SIZE;
+    }
+
+    if length <= MAXIMUM_BUCKET_SIZE {
+        return length.next_power_of_two();
+    }
+
+    length.div_ceil(MAXIMUM_BUCKET_SIZE) * MAXIMUM_BUCKET_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::random_server_key;
+    use crate::{data, Data, Message};
+    use aes_gcm::aead::{Aead, NewAead};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+    #[test]
+    fn it_pads_to_bucket_sizes() {
+        assert_eq!(pad(b"").len(), 256);
+        assert_eq!(pad(&[1; 255]).len(), 256);
+        assert_eq!(pad(&[1; 256]).len(), 512);
+        assert_eq!(pad(&[1; 70_000]).len(), 131_072);
+        assert_eq!(pad(&[1; 140_000]).len(), 196_608);
+    }
+
+    #[test]
+    fn it_removes_padding() {
+        assert_eq!(unpad(pad(b"")).unwrap(), b"");
+        assert_eq!(unpad(pad(b"testing\0\0")).unwrap(), b"testing\0\0");
+        assert_eq!(unpad(pad(&[0x80; 300])).unwrap(), vec![0x80; 300]);
+
+        assert!(unpad(vec![]).is_err());
+        assert!(unpad(vec![0, 0]).is_err());
+        assert!(unpad(vec![1, 0]).is_err());
+    }
+
+    #[test]
+    fn it_hides_the_data_type() {
+        let server_key = &random_server_key();
+
+        let ping = Message::new()
+            .set_server_id(b"esm_testing")
+            .set_data(Data::Ping);
+
+        let reward = Message::new()
+            .set_server_id(b"esm_testing")
+            .set_data(Data::Reward(data::Reward {
+                items: None,
+                locker_poptabs: Some(1.into()),
+                player_poptabs: Some(2.into()),
+                respect: Some(3.into()),
+                vehicles: None,
+            }));
+
+        assert_ne!(
+            ping.as_bytes(server_key).unwrap().len(),
+            reward.as_bytes(server_key).unwrap().len()
+        );
+
+        let ping_bytes = ping.as_padded_bytes(server_key).unwrap();
+        let reward_bytes = reward.as_padded_bytes(server_key).unwrap();
+        assert_eq!(ping_bytes.len(), reward_bytes.len());
+
+        let result = Message::from_bytes(&ping_bytes, server_key).unwrap();
+        assert_eq!(result.data, Data::Ping);
+
+        let result = Message::from_bytes(&reward_bytes, server_key).unwrap();
+        assert_eq!(result.data, reward.data);
+    }
+
+    #[test]
+    fn it_keeps_the_original_frame_without_padding() {
+        let server_key = &random_server_key();
+
+        let message = Message::new().set_server_id(b"esm_testing");
+        let bytes = message.as_bytes(server_key).unwrap();
+
+        // Decrypted the way a peer that predates flags does: no flags byte and nothing authenticated alongside
+        let nonce_index = 1 + 11 + 1;
+        assert_eq!(bytes[nonce_index - 1], 12);
+
+        let cipher = Aes256Gcm::new(Key::from_slice(&server_key[0..32]));
+        let nonce = Nonce::from_slice(&bytes[nonce_index..(nonce_index + 12)]);
+        let json = cipher.decrypt(nonce, &bytes[(nonce_index + 12)..]).unwrap();
+
+        let decrypted: Message = serde_json::from_slice(&json).unwrap();
+        assert_eq!(decrypted.id, message.id);
+
+        // Padded frames mark the nonce size, as only peers that know about flags can read them
+        let bytes = message.as_padded_bytes(server_key).unwrap();
+        assert_eq!(bytes[nonce_index - 1], 0x80 | 12);
+    }
+
+    #[test]
+    fn it_authenticates_the_flags() {
+        let server_key = &random_server_key();
+
+        let mut bytes = Message::new()
+            .set_server_id(b"esm_testing")
+            .as_padded_bytes(server_key)
+            .unwrap();
+
+        // 1 + server id + 1 + nonce
+        let flags_index = 1 + 11 + 1 + 12;
+        bytes[flags_index] = 0;
+
+        assert!(Message::from_bytes(&bytes, server_key).is_err());
+    }
+}
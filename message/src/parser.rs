@@ -1,172 +1,244 @@
+mod common;
+mod de;
+mod options;
+mod parse_error;
+mod ser;
+mod sqf;
+mod value;
+
+pub use de::{from_str, from_str_with_options, Deserializer};
+pub use options::{ParseMode, ParseOptions};
+pub use parse_error::{ParseError, ParseErrorKind};
+pub use ser::{to_arma_string, to_arma_value, SerializeError, Serializer};
+pub use value::{from_value, from_value_with_options};
+
+pub(crate) use sqf::split_elements;
+
+use arma_rs::Value as ArmaValue;
 use serde::de::DeserializeOwned;
 use serde_json::Value as JSONValue;
-use unicode_segmentation::UnicodeSegmentation;
+use std::collections::HashSet;
+
+/// An array starting with this string is always treated as a hash (key/value pairs), even when there is no type
+/// to say so -> ["#hash", ["key", value], ["key", value]]
+/// Without it, only the target type decides if an array of pairs is a hash or a list of pairs
+pub const HASH_MARKER: &str = "#hash";
 
 pub struct Parser {}
 
 impl Parser {
+    /// Deserializes Arma array text straight into `T`. Only the type decides if an array of pairs is a hash,
+    /// so a self-describing `T` such as serde_json::Value keeps a list of pairs as an array unless it starts
+    /// with HASH_MARKER. Use from_arma_value to get JSON with every list of pairs converted into an object
     pub fn from_arma<T: DeserializeOwned>(input: &str) -> Result<T, String> {
-        let input = replace_arma_characters(input);
-
-        let input: JSONValue = match serde_json::from_str(&input) {
-            Ok(v) => v,
-            Err(e) => {
-                return Err(format!(
-                    "[esm_message::parser::from_arma] Failed to convert input into JSON. Reason: {e}. Input: {input}"
-                ))
-            }
-        };
-
-        let json = validate_content(&input);
-        let json = match serde_json::to_string(&json) {
-            Ok(j) => j,
-            Err(e) => return Err(format!("[esm_message::parser::from_arma] Failed to convert to final JSON. Reason: {e}. Input: \"{input}\"")),
-        };
+        Self::from_arma_with_options(input, &ParseOptions::default())
+    }
 
-        let output: T = match serde_json::from_str(&json) {
-            Ok(t) => t,
-            Err(e) => return Err(format!("[esm_message::parser::from_arma] Failed to convert to Data/Metadata. Reason: {e}. Input: \"{input}\" ")),
-        };
+    pub fn from_arma_with_options<T: DeserializeOwned>(
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<T, String> {
+        match from_str_with_options(input, options) {
+            Ok(t) => Ok(t),
+            Err(e) => Err(format!(
+                "[esm_message::parser::from_arma] Failed to parse input. {e}"
+            )),
+        }
+    }
 
-        Ok(output)
+    /// Deserializes an arma_rs::Value into `T`. Errors are prefixed the same way as from_arma's
+    pub fn from_value<T: DeserializeOwned>(input: &ArmaValue) -> Result<T, String> {
+        match from_value(input) {
+            Ok(t) => Ok(t),
+            Err(e) => Err(format!(
+                "[esm_message::parser::from_value] Failed to convert input. {e}"
+            )),
+        }
     }
 }
 
-pub fn validate_content(input: &JSONValue) -> JSONValue {
-    match input {
-        JSONValue::Array(a) => {
-            if a.is_empty() {
-                JSONValue::Array(vec![])
-            } else {
-                match convert_arma_array_to_object(a) {
-                    Ok(v) => v,
-                    Err(_) => input.to_owned(),
-                }
-            }
+/// Converts Arma array text into JSON, the reverse of to_arma_string.
+/// Without a type to say what is a hash, arrays are converted to objects following validate_content's rules.
+///
+/// A value survives to_arma_string then from_arma_value unchanged, except for two shapes Arma cannot tell apart:
+/// an empty object comes back as an empty array, and an array made only of [string, value] pairs comes back as an object
+pub fn from_arma_value(input: &str) -> Result<JSONValue, ParseError> {
+    from_arma_value_with_options(input, &ParseOptions::default())
+}
+
+/// Same as from_arma_value, with control over how strictly the input is checked
+pub fn from_arma_value_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<JSONValue, ParseError> {
+    let value = sqf::parse(input, options)?;
+
+    // Unlike validate_content, lists are always searched for hashes, so a list of objects comes back as one
+    let mut validator = Validator {
+        options,
+        elements: 0,
+        search_lists: true,
+    };
+
+    validator.validate(&value, "", 1).map_err(|e| match e {
+        ValidateError::Limit(e) | ValidateError::Invalid(e) => {
+            <ParseError as serde::de::Error>::custom(e)
         }
-        _ => input.to_owned(),
-    }
+    })
 }
 
-fn convert_arma_array_to_object(input: &Vec<JSONValue>) -> Result<JSONValue, String> {
-    if !input
-        .iter()
-        .all(|i| i.is_array() && i.as_array().unwrap().len() == 2)
-    {
-        return Err(format!("[esm_message::parser::convert_arma_array_to_object] Input must consist of key/value pairs. Input: {input:?}"));
-    }
+/// Converts arrays of key/value pairs into objects, recursively.
+/// Without a type to guide it, any array where every element is a [string, value] pair is converted,
+/// so prefer parser::from_str with a concrete type when the data could contain lists of pairs
+pub fn validate_content(input: &JSONValue) -> JSONValue {
+    // Lenient mode only fails when a limit is hit, and then the input is left as it is
+    validate_content_with_options(input, &ParseOptions::lenient())
+        .unwrap_or_else(|_| input.to_owned())
+}
 
-    let mut object = serde_json::map::Map::new();
-    for pair in input {
-        let pair = match pair.as_array() {
-            Some(a) => a,
-            None => return Err(format!("[esm_message::parser::convert_arma_array_to_object] Failed to convert key/value pair. Pair: {pair:?}")),
-        };
+/// Same as validate_content, but in strict mode any hash that does not conform is an error naming where it is.
+/// A hash is an array starting with the hash marker, or an array whose first element is a [string, value] pair.
+/// The depth and element limits apply in both modes
+pub fn validate_content_with_options(
+    input: &JSONValue,
+    options: &ParseOptions,
+) -> Result<JSONValue, String> {
+    let mut validator = Validator {
+        options,
+        elements: 0,
+        search_lists: options.is_strict(),
+    };
+
+    validator.validate(input, "", 1).map_err(|e| match e {
+        ValidateError::Limit(e) | ValidateError::Invalid(e) => e,
+    })
+}
 
-        let key = match pair.get(0) {
-            Some(k) => match k.as_str() {
-                Some(k) => k,
-                None => return Err(format!("[esm_message::parser::convert_arma_array_to_object] Failed to convert key to string. Pair: {pair:?}"))
-            },
-            None => return Err(format!("[esm_message::parser::convert_arma_array_to_object] Failed to extract key from {pair:?}"))
-        };
+enum ValidateError {
+    // Always returned, even in lenient mode
+    Limit(String),
 
-        let value = match pair.get(1) {
-            Some(v) => v,
-            None => return Err(format!("[esm_message::parser::convert_arma_array_to_object] Failed to extract value from {pair:?}"))
-        };
+    // Only returned in strict mode. Lenient mode leaves the array as it is
+    Invalid(String),
+}
 
-        object.insert(key.to_string(), validate_content(value));
-    }
+struct Validator<'a> {
+    options: &'a ParseOptions,
+    elements: usize,
 
-    Ok(JSONValue::Object(object))
+    // Whether arrays that are not hashes are searched for hashes inside of them
+    search_lists: bool,
 }
 
-// Handles Arma's double quote escape characters and its various nil types
-fn replace_arma_characters(input: &str) -> String {
-    let str_terminators = ["[", "]", ",", ""];
-    let mut new_string_chars: Vec<String> = Vec::new();
-    let mut in_string = false;
-    let mut quote_series_counter = 1_usize;
-
-    let chars = input.graphemes(true).collect::<Vec<&str>>();
-    for (index, current_char) in chars.iter().enumerate() {
-        // This skips over the extra quotes in a series
-        if quote_series_counter.saturating_sub(1) > 0 {
-            quote_series_counter = quote_series_counter.saturating_sub(1);
-            continue;
+impl Validator<'_> {
+    fn validate(
+        &mut self,
+        input: &JSONValue,
+        path: &str,
+        depth: usize,
+    ) -> Result<JSONValue, ValidateError> {
+        let JSONValue::Array(array) = input else {
+            return Ok(input.to_owned());
         };
 
-        let mut char_to_add = current_char.to_string();
-        let previous_char = chars.get(index.saturating_sub(1)).unwrap_or(&"");
-        let next_char = chars.get(index.saturating_add(1)).unwrap_or(&"");
-
-        if current_char.eq(&"\"") {
-            if str_terminators.contains(previous_char) && !in_string {
-                in_string = true;
-            } else if str_terminators.contains(next_char) && in_string {
-                in_string = false;
-            } else if in_string {
-                // Detect how many double quotes are in this series and replace them with escape characters
-                for char in &chars[(index + 1)..] {
-                    if !char.eq(&"\"") {
-                        break;
-                    }
-
-                    quote_series_counter = quote_series_counter.saturating_add(1);
-                }
-
-                // There can only ever be a equal number of quotes to escape
-                // This handles an ending series of quotes -> """tada"""
-                if (quote_series_counter % 2) != 0 {
-                    quote_series_counter = quote_series_counter.saturating_sub(1);
-                }
-
-                char_to_add = format!("{}\"", "\\".repeat(quote_series_counter.saturating_sub(1)));
+        self.enforce_limits(array, path, depth)?;
+
+        if is_marked_hash(array) {
+            return match self.convert(&array[1..], path, depth) {
+                Err(ValidateError::Invalid(_)) if !self.options.is_strict() => Ok(input.to_owned()),
+                result => result,
+            };
+        }
+
+        if array.is_empty() {
+            return Ok(JSONValue::Array(vec![]));
+        }
+
+        match self.convert(array, path, depth) {
+            Err(ValidateError::Invalid(_)) if self.search_lists && !is_pair(&array[0]) => {
+                // Not a hash, but it may contain them
+                let elements = array
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| self.validate(v, &format!("{path}[{i}]"), depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(JSONValue::Array(elements))
             }
+            Err(ValidateError::Invalid(_)) if !self.options.is_strict() => Ok(input.to_owned()),
+            result => result,
         }
+    }
 
-        // Handles escaping the escape characters
-        if current_char.eq(&"\\") && !next_char.eq(&"\\") {
-            char_to_add = "\\\\".into();
+    fn enforce_limits(
+        &mut self,
+        array: &[JSONValue],
+        path: &str,
+        depth: usize,
+    ) -> Result<(), ValidateError> {
+        if depth > self.options.max_depth {
+            return Err(ValidateError::Limit(format!("[esm_message::parser::validate_content] Arrays are nested deeper than the limit of {} in {}", self.options.max_depth, location(path))));
         }
 
-        new_string_chars.push(char_to_add);
-
-        // Replaces `any`, `null`, and `<null>` that are not inside a string
-        // Replacement occurs when the last char is detected
-        if !in_string {
-            let allowed_prefix_chars = ["", " ", ",", "["];
-            let allowed_suffix_chars = ["]", "", " "];
-            let index = new_string_chars.len().saturating_sub(1);
-
-            let detect_and_replace_word = |word: &str, chars: &mut Vec<String>| {
-                let word_size = word.len() - 1;
-                let starting_index = index.saturating_sub(word_size);
-                let slice = &chars[starting_index..=index].join("");
-                let previous_char = &chars[starting_index.saturating_sub(1)];
-
-                if slice.eq(&word)
-                    && allowed_prefix_chars.contains(&previous_char.as_str())
-                    && allowed_suffix_chars.contains(next_char)
-                {
-                    for _ in 0..=word_size {
-                        chars.pop();
-                    }
-
-                    for c in "null".chars().map(String::from) {
-                        chars.push(c);
-                    }
-                }
+        self.elements += array.len();
+        if self.elements > self.options.max_elements {
+            return Err(ValidateError::Limit(format!("[esm_message::parser::validate_content] Input has more values than the limit of {}", self.options.max_elements)));
+        }
+
+        Ok(())
+    }
+
+    fn convert(
+        &mut self,
+        input: &[JSONValue],
+        path: &str,
+        depth: usize,
+    ) -> Result<JSONValue, ValidateError> {
+        if let Some(index) = input.iter().position(|i| !is_pair(i)) {
+            return Err(ValidateError::Invalid(format!("[esm_message::parser::convert_arma_array_to_object] Input must consist of key/value pairs. Element {index} in {} is not a [string, value] pair: {:?}", location(path), input[index])));
+        }
+
+        let mut object = serde_json::map::Map::new();
+        let mut seen = HashSet::new();
+        for pair in input {
+            // Checked above
+            let (Some(key), Some(value)) = (pair[0].as_str(), pair.get(1)) else {
+                continue;
             };
 
-            detect_and_replace_word("any", &mut new_string_chars);
-            detect_and_replace_word("nil", &mut new_string_chars);
-            detect_and_replace_word("<null>", &mut new_string_chars);
+            if self.options.is_strict() && !seen.insert(key) {
+                return Err(ValidateError::Invalid(format!("[esm_message::parser::convert_arma_array_to_object] Duplicate key {key:?} in {}", location(path))));
+            }
+
+            let value_path = if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{path}.{key}")
+            };
+
+            // The pair itself is one level deeper, its value is another
+            let value = self.validate(value, &value_path, depth + 2)?;
+            object.insert(key.to_string(), value);
         }
+
+        Ok(JSONValue::Object(object))
+    }
+}
+
+fn location(path: &str) -> &str {
+    if path.is_empty() {
+        "the top level"
+    } else {
+        path
     }
+}
+
+fn is_marked_hash(input: &[JSONValue]) -> bool {
+    matches!(input.first(), Some(JSONValue::String(s)) if s == HASH_MARKER)
+}
 
-    new_string_chars.join("")
+fn is_pair(input: &JSONValue) -> bool {
+    matches!(input.as_array(), Some(pair) if pair.len() == 2 && pair[0].is_string())
 }
 
 #[cfg(test)]
@@ -175,6 +247,7 @@ mod tests {
     use crate::{data, Data};
     use arma_rs::IntoArma;
     use serde_json::json;
+    use std::collections::HashMap;
 
     #[test]
     fn it_converts_arma_hash_correctly() {
@@ -197,6 +270,14 @@ mod tests {
         )
     }
 
+    #[test]
+    fn it_converts_marked_hashes() {
+        let input = json!(["#hash", ["key_1", "value_1"], ["key_2", ["#hash"]]]);
+
+        let result = validate_content(&input);
+        assert_eq!(result, json!({ "key_1": "value_1", "key_2": {} }));
+    }
+
     #[test]
     fn it_does_not_convert_empty_arrays() {
         let input = json!([]);
@@ -243,6 +324,232 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_reports_errors_against_the_original_input() {
+        let input = format!(
+            r#"[["type","sqf_result"],["content",[["result","{}"] ["extra",nil]]]]"#,
+            "a".repeat(1_000)
+        );
+
+        let error = Parser::from_arma::<Data>(&input).unwrap_err();
+        assert!(error.contains(
+            "Expected \",\" or \"]\" but found \"[\" at line 1, column 1050 (byte 1049)"
+        ));
+        assert!(error.len() < 500);
+
+        let input = format!(
+            r#"[["type","test"],["content",[["bar","{}"]]]]"#,
+            "a".repeat(1_000)
+        );
+        let error = Parser::from_arma::<Data>(&input).unwrap_err();
+        assert!(error.contains("missing field `foo` at line 1, column 29 (byte 28)"));
+        assert!(error.len() < 500);
+    }
+
+    #[test]
+    fn it_validates_strictly() {
+        let strict = ParseOptions::strict();
+
+        let input = json!(["#hash", ["key_1", "value_1"], "key_2"]);
+        assert_eq!(validate_content(&input), input);
+        let error = validate_content_with_options(&input, &strict).unwrap_err();
+        assert!(error.contains("Element 1 in the top level is not a [string, value] pair"));
+
+        let input = json!([["key_1", [["key_2", 1], ["key_3"]]]]);
+        assert_eq!(
+            validate_content(&input),
+            json!({ "key_1": [["key_2", 1], ["key_3"]] })
+        );
+        let error = validate_content_with_options(&input, &strict).unwrap_err();
+        assert!(error.contains("Element 1 in key_1 is not a [string, value] pair"));
+
+        let input = json!([1, [["key_1", 1], ["key_1", 2]]]);
+        let error = validate_content_with_options(&input, &strict).unwrap_err();
+        assert!(error.contains("Duplicate key \"key_1\" in [1]"));
+
+        // Conforming input is converted the same as lenient mode
+        let input = json!([1, [["key_1", ["#hash"]]], []]);
+        assert_eq!(
+            validate_content_with_options(&input, &strict).unwrap(),
+            json!([1, { "key_1": {} }, []])
+        );
+    }
+
+    #[test]
+    fn it_parses_with_options() {
+        let input = r#"[["type","reward"],["content",[["respect",1],["vehicles",[[["class","Exile_Car"],["count",1]]]]]]]"#;
+
+        let result: Data = Parser::from_arma(input).unwrap();
+        assert_eq!(
+            result,
+            Data::Reward(data::Reward {
+                items: None,
+                locker_poptabs: None,
+                player_poptabs: None,
+                respect: Some(1.into()),
+                vehicles: Some(vec![HashMap::from([
+                    ("class".to_string(), "Exile_Car".to_string()),
+                    ("count".to_string(), "1".to_string())
+                ])])
+            })
+        );
+
+        let error =
+            Parser::from_arma_with_options::<Data>(input, &ParseOptions::strict()).unwrap_err();
+        assert!(error.contains("invalid type: integer `1`, expected a string"));
+        assert!(error.contains(", in content.vehicles[0].count"));
+
+        let input = r#"[["type","reward"],["content",[["respect","1"],["vehicles",[[["class","Exile_Car"],["class","Exile_Bike"]]]]]]]"#;
+        let error =
+            Parser::from_arma_with_options::<Data>(input, &ParseOptions::strict()).unwrap_err();
+        assert!(error.contains("Duplicate key \"class\""));
+        assert!(error.contains(", in content.vehicles[0]"));
+
+        let input = r#"[["type","test"],["content",[["foo","bar"],["fooo","bar"]]]]"#;
+        assert!(Parser::from_arma::<Data>(input).is_ok());
+        let error =
+            Parser::from_arma_with_options::<Data>(input, &ParseOptions::strict()).unwrap_err();
+        assert!(error.contains("Unknown key \"fooo\", expected one of: foo"));
+        assert!(error.contains(", in content"));
+    }
+
+    #[test]
+    fn it_stops_at_the_limits() {
+        // Deep enough to overflow the stack without a limit
+        let input = format!("{}{}", "[".repeat(1_000_000), "]".repeat(1_000_000));
+        let error = Parser::from_arma::<JSONValue>(&input).unwrap_err();
+        assert!(error.contains("Arrays are nested deeper than the limit of 128"));
+
+        let options = ParseOptions {
+            max_input_length: 10,
+            ..Default::default()
+        };
+        let error = from_str_with_options::<Vec<u8>>("[1,2,3,4,5]", &options).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InputTooLong);
+
+        let options = ParseOptions {
+            max_elements: 4,
+            ..Default::default()
+        };
+        let error = from_str_with_options::<Vec<u8>>("[1,2,3,4,5]", &options).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::TooManyElements);
+
+        let mut input = json!(["key", "value"]);
+        for _ in 0..200 {
+            input = json!([input]);
+        }
+        assert!(
+            validate_content_with_options(&input, &ParseOptions::strict())
+                .unwrap_err()
+                .contains("Arrays are nested deeper than the limit of 128 in [0][0]")
+        );
+
+        let mut input = json!("value");
+        for _ in 0..100 {
+            input = json!([["key", input]]);
+        }
+        assert!(
+            validate_content_with_options(&input, &ParseOptions::lenient())
+                .unwrap_err()
+                .contains("Arrays are nested deeper than the limit of 128 in key.key")
+        );
+        assert_eq!(validate_content(&input), input);
+
+        let input = json!([["a", [1, 2]], ["b", [3, 4]]]);
+        assert!(validate_content_with_options(&input, &options)
+            .unwrap_err()
+            .contains("Input has more values than the limit of 4"));
+    }
+
+    #[test]
+    fn it_converts_json_to_arma_and_back() {
+        let value = json!({
+            "string": "with \"quotes\", 'single quotes' and \"\"doubled\"\" quotes",
+            "empty_string": "",
+            "number": 1.5,
+            "negative": -20,
+            "boolean": false,
+            "null": null,
+            "list": [1, "two", null, [true, [3]]],
+            "nested": {
+                "inner": { "deepest": "value", "nothing": null },
+                "list_of_objects": [{ "a": 1 }, { "b": [] }]
+            }
+        });
+
+        let arma = to_arma_string(&value).unwrap();
+        assert_eq!(from_arma_value(&arma).unwrap(), value);
+
+        // Scalars and lists round trip on their own as well
+        for value in [
+            json!(null),
+            json!("\""),
+            json!(5),
+            json!([]),
+            json!([[1, 2], null]),
+        ] {
+            let arma = to_arma_string(&value).unwrap();
+            assert_eq!(from_arma_value(&arma).unwrap(), value, "{arma}");
+        }
+    }
+
+    #[test]
+    fn it_documents_the_shapes_that_do_not_round_trip() {
+        let value = json!({ "empty": {}, "pairs": [["a", 1], ["b", 2]] });
+        let arma = to_arma_string(&value).unwrap();
+
+        assert_eq!(arma, r#"[["empty",[]],["pairs",[["a",1],["b",2]]]]"#);
+        assert_eq!(
+            from_arma_value(&arma).unwrap(),
+            json!({ "empty": [], "pairs": { "a": 1, "b": 2 } })
+        );
+    }
+
+    #[test]
+    fn it_only_reads_marked_hashes_as_objects_without_a_type() {
+        let input = r##"[["a",1],["b",[["c",2]]],["d",["#hash",["e",3]]]]"##;
+
+        assert_eq!(
+            Parser::from_arma::<JSONValue>(input).unwrap(),
+            json!([["a", 1], ["b", [["c", 2]]], ["d", { "e": 3 }]])
+        );
+
+        assert_eq!(
+            from_arma_value(input).unwrap(),
+            json!({ "a": 1, "b": { "c": 2 }, "d": { "e": 3 } })
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_key_order_from_arma() {
+        let input = r#"[["b",1],["a",2],["c",[["z",1],["y",2]]]]"#;
+        let value = from_arma_value(input).unwrap();
+
+        assert_eq!(value.to_string(), r#"{"b":1,"a":2,"c":{"z":1,"y":2}}"#);
+        assert_eq!(to_arma_string(&value).unwrap(), input);
+    }
+
+    #[test]
+    fn it_reads_arma_values() {
+        let result = from_arma_value(
+            r##"[["key", "a ""quoted"" value"], ["hash", ["#hash"]], ["list", [nil, 1e+001]]]"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result,
+            json!({ "key": "a \"quoted\" value", "hash": {}, "list": [null, 10.0] })
+        );
+
+        let error = from_arma_value(r#"[["key", "value"]"#).unwrap_err();
+        assert_eq!(error.offset, 17);
+
+        let error =
+            from_arma_value_with_options(r#"[["key", 1], ["key", 2]]"#, &ParseOptions::strict())
+                .unwrap_err();
+        assert!(error.message.contains("Duplicate key \"key\""));
+    }
+
     #[test]
     fn it_handles_null_characters() {
         let input = r#"[["type","reward"],["content",[["items",<null>],["locker_poptabs",nil],["player_poptabs",any],["respect","1"],["vehicles",[]]]]]"#;
@@ -253,7 +560,7 @@ mod tests {
                 items: None,
                 locker_poptabs: None,
                 player_poptabs: None,
-                respect: Some("1".to_string()),
+                respect: Some(1.into()),
                 vehicles: Some(vec![])
             })
         );
@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+
+use serde::de::Visitor;
+use serde_json::Number;
+
+use super::sqf;
+use super::{ParseError, ParseOptions};
+use crate::number_string::NumberString;
+
+/*
+    The rules both deserializers follow, whether they read Arma's text (de.rs) or a value arma-rs has already
+    parsed (value.rs). Each of them only converts what it has into the types here, so the two can not drift apart.
+*/
+
+// Every integer up to 2^53 fits in an f64 exactly. Past that, a whole float may not be the number that was sent
+pub(super) const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+// Lenient mode can coerce another kind of scalar into these
+macro_rules! deserialize_coerced {
+    ($($method:ident => $target:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+                self.deserialize_coerced(Scalar::$target, visitor)
+            }
+        )*
+    };
+}
+
+pub(super) use deserialize_coerced;
+
+#[derive(Clone, Copy)]
+pub(super) enum Scalar {
+    Bool,
+    Integer,
+    Float,
+    String,
+}
+
+// A scalar as it was found in the input. Numbers are kept as text so their digits can be checked
+pub(super) enum Found<'a> {
+    Number(&'a str),
+    String(&'a str),
+    Bool(bool),
+}
+
+pub(super) enum Coerced {
+    Bool(bool),
+    Number(Number),
+    Exact(NumberString),
+    String(String),
+    LostPrecision(String),
+}
+
+/*
+    Arma is loose with its types, a number can arrive as "1" and a string as 1.
+    In lenient mode, a scalar is coerced when the requested type asks for a different kind of scalar.
+    In both modes, integers are read from the number's text so digits lost in Arma are an error, not a rounded value.
+    None means the scalar is read as it is
+*/
+pub(super) fn coerce(target: Scalar, found: Found, options: &ParseOptions) -> Option<Coerced> {
+    let is_strict = options.is_strict();
+
+    match (target, found) {
+        (Scalar::Integer, Found::Number(n)) => exact_number(n),
+        (Scalar::Integer, Found::String(s)) if !is_strict => exact_number(s),
+        _ if is_strict => None,
+        (Scalar::String, Found::Number(n)) => match exact_number(n) {
+            Some(Coerced::Exact(n)) => Some(Coerced::String(n.into_string())),
+            coerced => coerced,
+        },
+        (Scalar::String, Found::Bool(b)) => Some(Coerced::String(b.to_string())),
+        (Scalar::Bool, Found::String(s)) => s.to_lowercase().parse().ok().map(Coerced::Bool),
+        (Scalar::Float, Found::String(s)) => sqf::parse_number(s.trim()).map(Coerced::Number),
+        _ => None,
+    }
+}
+
+// LostPrecision has to be turned into an error by the caller first, as only it knows where the number is
+pub(super) fn visit_coerced<'de, V: Visitor<'de>>(
+    coerced: Coerced,
+    visitor: V,
+) -> Result<V::Value, ParseError> {
+    match coerced {
+        Coerced::Bool(b) => visitor.visit_bool(b),
+        Coerced::Number(n) => visit_number(n, visitor),
+        Coerced::Exact(n) => visit_exact_number(&n, visitor),
+        Coerced::String(s) => visitor.visit_string(s),
+        Coerced::LostPrecision(text) => Err(<ParseError as serde::de::Error>::custom(
+            lost_precision_message(&text),
+        )),
+    }
+}
+
+// Reads a number from its text, without going through a float
+fn exact_number(text: &str) -> Option<Coerced> {
+    if sqf::has_lost_precision(text) {
+        return Some(Coerced::LostPrecision(text.to_string()));
+    }
+
+    text.parse().ok().map(Coerced::Exact)
+}
+
+pub(super) fn lost_precision_message(text: &str) -> String {
+    format!("{text:?} has lost precision. Arma only keeps 6 significant digits of a number, send it as a string made with toFixed instead")
+}
+
+pub(super) fn visit_exact_number<'de, V: Visitor<'de>>(
+    number: &NumberString,
+    visitor: V,
+) -> Result<V::Value, ParseError> {
+    if let Ok(n) = number.to_u64() {
+        visitor.visit_u64(n)
+    } else if let Ok(n) = number.to_i64() {
+        visitor.visit_i64(n)
+    } else if let Ok(n) = number.parse::<i128>() {
+        visitor.visit_i128(n)
+    } else if let Ok(n) = number.parse::<u128>() {
+        visitor.visit_u128(n)
+    } else {
+        // Has a fraction, so the visitor can say it wanted an integer
+        visitor.visit_f64(number.to_f64().unwrap_or_default())
+    }
+}
+
+pub(super) fn visit_number<'de, V: Visitor<'de>>(
+    number: Number,
+    visitor: V,
+) -> Result<V::Value, ParseError> {
+    if let Some(n) = number.as_u64() {
+        visitor.visit_u64(n)
+    } else if let Some(n) = number.as_i64() {
+        visitor.visit_i64(n)
+    } else {
+        visitor.visit_f64(number.as_f64().unwrap_or_default())
+    }
+}
+
+// Strict mode only accepts keys the target knows about, and each of them once
+pub(super) fn check_key(
+    options: &ParseOptions,
+    key: &str,
+    fields: &[&str],
+    seen: &mut HashSet<String>,
+) -> Result<(), String> {
+    if !options.is_strict() {
+        return Ok(());
+    }
+
+    if !fields.is_empty() && !fields.contains(&key) {
+        return Err(format!(
+            "Unknown key {key:?}, expected one of: {}",
+            fields.join(", ")
+        ));
+    }
+
+    if !seen.insert(key.to_string()) {
+        return Err(format!("Duplicate key {key:?}"));
+    }
+
+    Ok(())
+}
+
+/*
+    Decides if an array starting with a list of string keys is the [[keys], [values]] form that SQF's toArray
+    creates for a HashMap. Only the first value is needed, so nothing past it has to be read to decide.
+
+    [["a", "b"], ["c", "d"]] is valid as both forms. In that case, it is only read as keys and values when
+    every key is a field on the struct being deserialized. Otherwise, the key/value pair form wins.
+*/
+pub(super) fn is_keys_and_values<K: AsRef<str>>(
+    keys: &[K],
+    first_value_is_string: bool,
+    fields: &[&str],
+) -> bool {
+    let is_ambiguous = keys.len() == 2 && first_value_is_string;
+    !is_ambiguous || keys.iter().all(|k| fields.contains(&k.as_ref()))
+}
@@ -0,0 +1,1081 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::Value as JSONValue;
+
+use super::common::{self, deserialize_coerced, Coerced, Found, Scalar};
+use super::sqf::{self, Lexer, Token};
+use super::{ParseError, ParseErrorKind, ParseOptions, HASH_MARKER};
+use crate::number_string::NUMBER_STRING;
+
+/// Deserializes Arma array text straight into `T`, without converting it to JSON first.
+/// Arrays of key/value pairs are read as maps whenever `T` asks for a struct or map
+pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T, ParseError> {
+    from_str_with_options(input, &ParseOptions::default())
+}
+
+/// Same as from_str, with control over how strictly the input has to match `T`
+pub fn from_str_with_options<'de, T: de::Deserialize<'de>>(
+    input: &'de str,
+    options: &ParseOptions,
+) -> Result<T, ParseError> {
+    sqf::check_input_length(input, options)?;
+
+    let mut deserializer = Deserializer::with_options(input, options.clone());
+
+    let value = T::deserialize(&mut deserializer).map_err(|e| e.at(input, 0))?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+// The keys of a [[keys], [values]] map and where each of them is in the input
+type Keys<'de> = Vec<(usize, Cow<'de, str>)>;
+
+pub struct Deserializer<'de> {
+    lexer: Lexer<'de>,
+    options: ParseOptions,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Deserializer::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'de str, options: ParseOptions) -> Self {
+        Deserializer {
+            lexer: Lexer::with_options(input, &options),
+            options,
+        }
+    }
+
+    /// Ensures nothing but whitespace is left in the input
+    pub fn end(&mut self) -> Result<(), ParseError> {
+        match self.lexer.next_token()? {
+            (_, Token::End) => Ok(()),
+            (position, token) => Err(self.lexer.error(position, "the end of input", token)),
+        }
+    }
+
+    fn expect(&mut self, expected: Token<'static>, description: &str) -> Result<usize, ParseError> {
+        let (position, token) = self.lexer.next_token()?;
+        if token == expected {
+            return Ok(position);
+        }
+
+        Err(self.lexer.error(position, description, token))
+    }
+
+    fn read_map<V: Visitor<'de>>(
+        &mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let position = self.expect(Token::OpenBracket, "an array of key/value pairs")?;
+        let is_hash = self.skip_hash_marker()?;
+
+        let keys_and_values = if is_hash {
+            None
+        } else {
+            self.scan_keys_and_values(fields)
+        };
+
+        // SQF HashMap converted with toArray
+        if let Some((keys, values_lexer)) = keys_and_values {
+            self.lexer = values_lexer;
+
+            let value = visitor
+                .visit_map(KeysValues {
+                    de: self,
+                    keys: keys.into_iter(),
+                    fields,
+                    seen: HashSet::new(),
+                    key: None,
+                    first: true,
+                })
+                .map_err(|e| e.at(input, position))?;
+
+            self.expect(Token::CloseBracket, "\"]\" ending the values")?;
+            self.expect(Token::CloseBracket, "\"]\"")?;
+            return Ok(value);
+        }
+
+        let value = visitor
+            .visit_map(Pairs {
+                de: self,
+                fields,
+                seen: HashSet::new(),
+                key: None,
+                first: !is_hash,
+            })
+            .map_err(|e| e.at(input, position))?;
+
+        self.expect(Token::CloseBracket, "\"]\"")?;
+        Ok(value)
+    }
+
+    fn check_key(
+        &self,
+        position: usize,
+        key: &str,
+        fields: &[&str],
+        seen: &mut HashSet<String>,
+    ) -> Result<(), ParseError> {
+        common::check_key(&self.options, key, fields, seen)
+            .map_err(|message| ParseError::new(self.lexer.input(), position, message))
+    }
+
+    fn deserialize_coerced<V: Visitor<'de>>(
+        &mut self,
+        target: Scalar,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let (position, token) = self.lexer.peek_token()?;
+        let position = *position;
+
+        let found = match token {
+            Token::Number(n) => Some(Found::Number(n)),
+            Token::String(s) => Some(Found::String(s)),
+            Token::Word(w) => match sqf::parse_word(w) {
+                Some(JSONValue::Bool(b)) => Some(Found::Bool(b)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        let Some(coerced) = found.and_then(|found| common::coerce(target, found, &self.options))
+        else {
+            return de::Deserializer::deserialize_any(self, visitor);
+        };
+
+        if let Coerced::LostPrecision(text) = coerced {
+            return Err(lost_precision(input, position, &text));
+        }
+
+        self.lexer.next_token()?;
+        common::visit_coerced(coerced, visitor).map_err(|e| e.at(input, position))
+    }
+
+    /*
+        Called after the opening bracket of a map. Looks ahead to see if the map is in the [[keys], [values]] form
+        that SQF's toArray creates for a HashMap. Returns the keys and a lexer positioned at the start of the values.
+        Only the keys and the first value's token are read, the values are read once, by KeysValues.
+        A value for every key is checked as they are read, rather than by counting them here
+    */
+    fn scan_keys_and_values(&self, fields: &[&str]) -> Option<(Keys<'de>, Lexer<'de>)> {
+        let mut lexer = self.lexer.clone();
+
+        if lexer.next_token().ok()?.1 != Token::OpenBracket {
+            return None;
+        }
+
+        let mut keys = vec![];
+        loop {
+            match lexer.next_token().ok()? {
+                (_, Token::CloseBracket) if keys.is_empty() => break,
+                (position, Token::String(key)) => keys.push((position, key)),
+                _ => return None,
+            }
+
+            match lexer.next_token().ok()?.1 {
+                Token::Comma => continue,
+                Token::CloseBracket => break,
+                _ => return None,
+            }
+        }
+
+        if lexer.next_token().ok()?.1 != Token::Comma
+            || lexer.next_token().ok()?.1 != Token::OpenBracket
+        {
+            return None;
+        }
+
+        let first_value_is_string = matches!(lexer.peek_token().ok()?.1, Token::String(_));
+
+        let key_names = keys.iter().map(|(_, k)| k.as_ref()).collect::<Vec<_>>();
+        if !common::is_keys_and_values(&key_names, first_value_is_string, fields) {
+            return None;
+        }
+
+        Some((keys, lexer))
+    }
+
+    // Called after an opening bracket. Consumes the hash marker if it is the first element
+    fn skip_hash_marker(&mut self) -> Result<bool, ParseError> {
+        if !matches!(&self.lexer.peek_token()?.1, Token::String(s) if s == HASH_MARKER) {
+            return Ok(false);
+        }
+
+        self.lexer.next_token()?;
+        Ok(true)
+    }
+
+    fn peek_position(&mut self) -> Result<usize, ParseError> {
+        Ok(self.lexer.peek_token()?.0)
+    }
+
+    fn peek_is_close_bracket(&mut self) -> Result<bool, ParseError> {
+        Ok(matches!(self.lexer.peek_token()?.1, Token::CloseBracket))
+    }
+
+    fn peek_is_null(&mut self) -> Result<bool, ParseError> {
+        Ok(match &self.lexer.peek_token()?.1 {
+            Token::Word(w) => sqf::parse_word(w) == Some(JSONValue::Null),
+            _ => false,
+        })
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let position = self.peek_position()?;
+
+        // Without a type to guide it, an array is only read as a map if it starts with the hash marker
+        if matches!(self.lexer.peek_token()?.1, Token::OpenBracket) {
+            self.lexer.next_token()?;
+            let is_hash = self.skip_hash_marker()?;
+
+            let value = if is_hash {
+                visitor.visit_map(Pairs {
+                    de: self,
+                    fields: &[],
+                    seen: HashSet::new(),
+                    key: None,
+                    first: false,
+                })
+            } else {
+                visitor.visit_seq(Elements {
+                    de: self,
+                    index: 0,
+                    first: true,
+                })
+            }
+            .map_err(|e| e.at(input, position))?;
+
+            self.expect(Token::CloseBracket, "\"]\"")?;
+            return Ok(value);
+        }
+
+        let result: Result<V::Value, ParseError> = match self.lexer.next_token()?.1 {
+            Token::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Token::String(Cow::Owned(s)) => visitor.visit_string(s),
+            Token::Number(n) => match sqf::parse_number(n) {
+                Some(n) => common::visit_number(n, visitor),
+                None => return Err(self.lexer.number_error(position, n)),
+            },
+            Token::Word(w) => match sqf::parse_word(w) {
+                Some(JSONValue::Bool(b)) => visitor.visit_bool(b),
+                Some(_) => visitor.visit_unit(),
+                None => {
+                    return Err(self.lexer.error(
+                        position,
+                        "a string, number, boolean, nil or array",
+                        format!("{w:?}"),
+                    ))
+                }
+            },
+            token => return Err(self.lexer.error(position, "a value", token)),
+        };
+
+        result.map_err(|e| e.at(input, position))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let position = self.peek_position()?;
+
+        let result = if self.peek_is_null()? {
+            self.lexer.next_token()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        };
+
+        result.map_err(|e| e.at(input, position))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let (position, token) = self.lexer.next_token()?;
+
+        if let Token::Word(w) = &token {
+            if sqf::parse_word(w) == Some(JSONValue::Null) {
+                return visitor
+                    .visit_unit::<ParseError>()
+                    .map_err(|e| e.at(input, position));
+            }
+        }
+
+        Err(self.lexer.error(position, "nil", token))
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        if name != NUMBER_STRING {
+            return visitor.visit_newtype_struct(self);
+        }
+
+        // NumberString asks by name so its text can be checked before anything turns it into a float
+        let input = self.lexer.input();
+        let text = match self.lexer.peek_token()? {
+            (position, Token::Number(n)) => Some((*position, Cow::Borrowed(*n))),
+            (position, Token::String(s)) => Some((*position, s.clone())),
+            _ => None,
+        };
+
+        let Some((position, text)) = text else {
+            return self.deserialize_any(visitor);
+        };
+
+        if sqf::has_lost_precision(&text) {
+            return Err(lost_precision(input, position, &text));
+        }
+
+        self.lexer.next_token()?;
+        visitor
+            .visit_str::<ParseError>(&text)
+            .map_err(|e| e.at(input, position))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let position = self.expect(Token::OpenBracket, "an array")?;
+
+        // Allows a list of pairs, such as Vec<(String, String)>, to be read from a marked hash
+        let is_hash = self.skip_hash_marker()?;
+
+        let value = visitor
+            .visit_seq(Elements {
+                de: self,
+                index: 0,
+                first: !is_hash,
+            })
+            .map_err(|e| e.at(input, position))?;
+
+        self.expect(Token::CloseBracket, "\"]\"")?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_seq(visitor)
+    }
+
+    // [["key", value], ["key", value]] or [["key", "key"], [value, value]]
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        self.read_map(&[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.read_map(fields, visitor)
+    }
+
+    // "variant" or [["variant", value]]
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let input = self.lexer.input();
+        let (position, token) = self.lexer.next_token()?;
+
+        match token {
+            Token::String(s) => visitor
+                .visit_enum(IntoDeserializer::<ParseError>::into_deserializer(
+                    s.into_owned(),
+                ))
+                .map_err(|e| e.at(input, position)),
+            Token::OpenBracket => {
+                self.expect(Token::OpenBracket, "a [\"variant\", value] pair")?;
+
+                let value = visitor
+                    .visit_enum(Variant { de: self })
+                    .map_err(|e| e.at(input, position))?;
+
+                self.expect(
+                    Token::CloseBracket,
+                    "\"]\" ending the [\"variant\", value] pair",
+                )?;
+                self.expect(Token::CloseBracket, "\"]\"")?;
+                Ok(value)
+            }
+            token => {
+                Err(self
+                    .lexer
+                    .error(position, "a variant name or [[\"variant\", value]]", token))
+            }
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        sqf::parse_value(&mut self.lexer)?;
+        visitor.visit_unit()
+    }
+
+    deserialize_coerced! {
+        deserialize_bool => Bool,
+        deserialize_i8 => Integer,
+        deserialize_i16 => Integer,
+        deserialize_i32 => Integer,
+        deserialize_i64 => Integer,
+        deserialize_i128 => Integer,
+        deserialize_u8 => Integer,
+        deserialize_u16 => Integer,
+        deserialize_u32 => Integer,
+        deserialize_u64 => Integer,
+        deserialize_u128 => Integer,
+        deserialize_f32 => Float,
+        deserialize_f64 => Float,
+        deserialize_str => String,
+        deserialize_string => String
+    }
+
+    forward_to_deserialize_any! {
+        char bytes byte_buf identifier
+    }
+}
+
+fn lost_precision(input: &str, offset: usize, text: &str) -> ParseError {
+    ParseError::new(input, offset, common::lost_precision_message(text))
+        .with_kind(ParseErrorKind::LostPrecision)
+}
+
+struct Elements<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    index: usize,
+    first: bool,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for Elements<'a, 'de> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ParseError> {
+        if self.de.peek_is_close_bracket()? {
+            return Ok(None);
+        }
+
+        if !self.first {
+            self.de.expect(Token::Comma, "\",\" or \"]\"")?;
+        }
+
+        let index = self.index;
+        self.index += 1;
+        self.first = false;
+
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.in_index(index))
+    }
+}
+
+struct Pairs<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    fields: &'static [&'static str],
+    seen: HashSet<String>,
+
+    // The key of the pair being read, so errors in its value can name it
+    key: Option<Cow<'de, str>>,
+    first: bool,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for Pairs<'a, 'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ParseError> {
+        if self.de.peek_is_close_bracket()? {
+            return Ok(None);
+        }
+
+        if !self.first {
+            self.de.expect(Token::Comma, "\",\" or \"]\"")?;
+        }
+
+        self.first = false;
+        self.de.expect(Token::OpenBracket, "a [key, value] pair")?;
+
+        self.key = match self.de.lexer.peek_token()? {
+            (position, Token::String(key)) => {
+                let (position, key) = (*position, key.clone());
+                self.de
+                    .check_key(position, &key, self.fields, &mut self.seen)?;
+
+                Some(key)
+            }
+            _ => None,
+        };
+
+        let key = seed.deserialize(&mut *self.de)?;
+        self.de
+            .expect(Token::Comma, "\",\" between the key and value")?;
+
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ParseError> {
+        let value = seed
+            .deserialize(&mut *self.de)
+            .map_err(|e| match &self.key {
+                Some(key) => e.in_key(key),
+                None => e,
+            })?;
+
+        self.de
+            .expect(Token::CloseBracket, "\"]\" ending the [key, value] pair")?;
+
+        Ok(value)
+    }
+}
+
+struct KeysValues<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    keys: <Keys<'de> as IntoIterator>::IntoIter,
+    fields: &'static [&'static str],
+    seen: HashSet<String>,
+    key: Option<Cow<'de, str>>,
+    first: bool,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for KeysValues<'a, 'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ParseError> {
+        let Some((position, key)) = self.keys.next() else {
+            return Ok(None);
+        };
+
+        self.de
+            .check_key(position, &key, self.fields, &mut self.seen)?;
+        self.key = Some(key.clone());
+
+        seed.deserialize(IntoDeserializer::<ParseError>::into_deserializer(
+            key.into_owned(),
+        ))
+        .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ParseError> {
+        if self.de.peek_is_close_bracket()? {
+            let position = self.de.peek_position()?;
+            return Err(ParseError::new(
+                self.de.lexer.input(),
+                position,
+                "Expected a value for each key, but there are fewer values than keys",
+            ));
+        }
+
+        if !self.first {
+            self.de.expect(Token::Comma, "\",\"")?;
+        }
+
+        self.first = false;
+        seed.deserialize(&mut *self.de)
+            .map_err(|e| match &self.key {
+                Some(key) => e.in_key(key),
+                None => e,
+            })
+    }
+}
+
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'a, 'de> de::EnumAccess<'de> for Variant<'a, 'de> {
+    type Error = ParseError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), ParseError> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        self.de
+            .expect(Token::Comma, "\",\" between the variant and value")?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'a, 'de> de::VariantAccess<'de> for Variant<'a, 'de> {
+    type Error = ParseError;
+
+    fn unit_variant(self) -> Result<(), ParseError> {
+        de::Deserialize::deserialize(&mut *self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ParseError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        de::Deserializer::deserialize_seq(&mut *self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.de.read_map(fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data, metadata, Data, Error, ErrorType, Metadata};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Everything {
+        string: String,
+        number: i32,
+        float: f64,
+        boolean: bool,
+        optional: Option<String>,
+        missing: Option<String>,
+        list: Vec<u8>,
+        tuple: (String, i64),
+        map: HashMap<String, String>,
+        nested: Vec<Nested>,
+        kind: Kind,
+        newtype: Kind,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Nested {
+        key: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind {
+        Unit,
+        Newtype(String),
+    }
+
+    #[test]
+    fn it_deserializes_structs() {
+        let input = r#"[
+            ["string", "a ""quoted"" string"],
+            ["number", -5],
+            ["float", 1.5],
+            ["boolean", true],
+            ["optional", "some"],
+            ["list", [1, 2, 3]],
+            ["tuple", ["one", 2]],
+            ["map", [["key_1", "value_1"], ["key_2", "value_2"]]],
+            ["nested", [[["key", "one"]], [["key", "two"]]]],
+            ["kind", "unit"],
+            ["newtype", [["newtype", "value"]]],
+            ["ignored", [1, [2, "3"]]]
+        ]"#;
+
+        let result: Everything = from_str(input).unwrap();
+        assert_eq!(
+            result,
+            Everything {
+                string: "a \"quoted\" string".into(),
+                number: -5,
+                float: 1.5,
+                boolean: true,
+                optional: Some("some".into()),
+                missing: None,
+                list: vec![1, 2, 3],
+                tuple: ("one".into(), 2),
+                map: HashMap::from([
+                    ("key_1".into(), "value_1".into()),
+                    ("key_2".into(), "value_2".into())
+                ]),
+                nested: vec![Nested { key: "one".into() }, Nested { key: "two".into() }],
+                kind: Kind::Unit,
+                newtype: Kind::Newtype("value".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn it_deserializes_data_metadata_and_errors() {
+        let result: Data = from_str(r#"[["type","test"],["content",[["foo","bar"]]]]"#).unwrap();
+        assert_eq!(result, Data::Test(data::Test { foo: "bar".into() }));
+
+        let result: Data = from_str(r#"[["type","ping"]]"#).unwrap();
+        assert_eq!(result, Data::Ping);
+
+        let result: Data = from_str(r#"[["type","pong"],["content",nil]]"#).unwrap();
+        assert_eq!(result, Data::Pong);
+
+        let result: Metadata =
+            from_str(r#"[["type","test"],["content",[["foo","bar"]]]]"#).unwrap();
+        assert_eq!(result, Metadata::Test(metadata::Test { foo: "bar".into() }));
+
+        let result: Vec<Error> = from_str(
+            r#"[[["type","code"],["content","CODE"]],[["type","message"],["content","Oops"]]]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Error::new(ErrorType::Code, "CODE".into()),
+                Error::new(ErrorType::Message, "Oops".into())
+            ]
+        );
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct PairShaped {
+        bounds: Vec<Vec<f64>>,
+        positions: Vec<(f64, f64)>,
+        items: Vec<(String, u32)>,
+        rows: Vec<Vec<String>>,
+        lookup: HashMap<String, Vec<(String, String)>>,
+    }
+
+    #[test]
+    fn it_keeps_pair_shaped_lists_when_the_type_asks_for_a_list() {
+        let result: Vec<Vec<String>> = from_str(r#"[["a","b"],["c","d"]]"#).unwrap();
+        assert_eq!(result, vec![vec!["a", "b"], vec!["c", "d"]]);
+
+        let value = PairShaped {
+            bounds: vec![vec![0.0, 0.0], vec![100.5, 200.0]],
+            positions: vec![(1.0, 2.0), (3.0, 4.0)],
+            items: vec![("Exile_Item_Beer".into(), 2), ("Exile_Item_Can".into(), 1)],
+            rows: vec![vec!["key".into(), "value".into()]],
+            lookup: HashMap::from([("a".into(), vec![("b".into(), "c".into())])]),
+        };
+
+        let arma = crate::parser::to_arma_string(&value).unwrap();
+        assert_eq!(from_str::<PairShaped>(&arma).unwrap(), value);
+    }
+
+    #[test]
+    fn it_only_converts_marked_hashes_without_a_type() {
+        let result: JSONValue = from_str(r#"[["a","b"],["c","d"]]"#).unwrap();
+        assert_eq!(result, serde_json::json!([["a", "b"], ["c", "d"]]));
+
+        let result: JSONValue =
+            from_str(r##"["#hash",["a","b"],["c",["#hash",["d",[["e","f"]]]]]]"##).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({"a": "b", "c": {"d": [["e", "f"]]}})
+        );
+
+        let result: JSONValue = from_str(r##"["#hash"]"##).unwrap();
+        assert_eq!(result, serde_json::json!({}));
+
+        // Typed values accept the marker as well
+        let result: HashMap<String, String> = from_str(r##"["#hash",["a","b"]]"##).unwrap();
+        assert_eq!(result, HashMap::from([("a".into(), "b".into())]));
+
+        let result: Vec<(String, String)> = from_str(r##"["#hash",["a","b"]]"##).unwrap();
+        assert_eq!(result, vec![("a".into(), "b".into())]);
+
+        let result: Data =
+            from_str(r##"["#hash",["type","test"],["content",["#hash",["foo","bar"]]]]"##).unwrap();
+        assert_eq!(result, Data::Test(data::Test { foo: "bar".into() }));
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Scalars {
+        string: String,
+        boolean: bool,
+        number: u32,
+        float: f32,
+    }
+
+    #[test]
+    fn it_coerces_scalars_in_lenient_mode() {
+        let input = r#"[["string",1.5],["boolean","TRUE"],["number"," 42"],["float","-1e2"]]"#;
+        let result: Scalars = from_str(input).unwrap();
+        assert_eq!(
+            result,
+            Scalars {
+                string: "1.5".into(),
+                boolean: true,
+                number: 42,
+                float: -100.0
+            }
+        );
+
+        let result: Scalars =
+            from_str(r#"[["string",false],["boolean",true],["number",1],["float",2]]"#).unwrap();
+        assert_eq!(result.string, "false");
+
+        let error = from_str_with_options::<Scalars>(input, &ParseOptions::strict()).unwrap_err();
+        assert_eq!(
+            error.message,
+            "invalid type: floating point `1.5`, expected a string"
+        );
+        assert_eq!(error.path, "string");
+
+        // Only scalars are coerced
+        let error = from_str::<Scalars>(r#"[["string",[]]]"#).unwrap_err();
+        assert_eq!(error.path, "string");
+        let error = from_str::<Scalars>(r#"[["string",""],["boolean","yes"]]"#).unwrap_err();
+        assert_eq!(error.path, "boolean");
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Balances {
+        poptabs: Option<u64>,
+        uid: Option<String>,
+        respect: Option<crate::NumberString>,
+        ratio: Option<f32>,
+    }
+
+    #[test]
+    fn it_reads_arma_numbers_exactly() {
+        let input =
+            r#"[["poptabs",1.5e+001],["uid",1.25e+002],["respect","2.5e+001"],["ratio",1.5e+009]]"#;
+        let result: Balances = from_str(input).unwrap();
+        assert_eq!(
+            result,
+            Balances {
+                poptabs: Some(15),
+                uid: Some("125".into()),
+                respect: Some(25.into()),
+                ratio: Some(1.5e9)
+            }
+        );
+
+        let result: Balances = from_str(r#"[["poptabs","1500"],["uid",-0.000]]"#).unwrap();
+        assert_eq!(result.poptabs, Some(1500));
+        assert_eq!(result.uid, Some("0".into()));
+
+        // Integers do not need coercing, strict mode reads them exactly as well
+        let result: Balances =
+            from_str_with_options(r#"[["poptabs",1.5e+001]]"#, &ParseOptions::strict()).unwrap();
+        assert_eq!(result.poptabs, Some(15));
+    }
+
+    #[test]
+    fn it_rejects_numbers_that_lost_precision() {
+        for input in [
+            r#"[["poptabs",1.23457e+009]]"#,
+            r#"[["poptabs","1.23457e+009"]]"#,
+            r#"[["uid",7.65612e+016]]"#,
+            r#"[["respect",1.23457e+009]]"#,
+            r#"[["respect","1.23457e+009"]]"#,
+        ] {
+            let error = from_str::<Balances>(input).unwrap_err();
+            assert_eq!(error.kind, ParseErrorKind::LostPrecision, "{input}");
+            assert!(
+                error.message.ends_with("has lost precision. Arma only keeps 6 significant digits of a number, send it as a string made with toFixed instead"),
+                "{input}"
+            );
+        }
+
+        let error = from_str::<Balances>(r#"[["poptabs",1.23457e+009]]"#).unwrap_err();
+        assert_eq!(error.path, "poptabs");
+        assert_eq!(error.offset, 12);
+
+        // Floats are never exact to begin with
+        let result: Balances = from_str(r#"[["ratio",1.23457e+009]]"#).unwrap();
+        assert_eq!(result.ratio, Some(1.23457e9));
+
+        let result: Vec<f64> = from_str("[1.23457e+009]").unwrap();
+        assert_eq!(result, vec![1.23457e9]);
+    }
+
+    #[test]
+    fn it_names_the_path_of_errors() {
+        let error =
+            from_str::<HashMap<String, Vec<Nested>>>(r#"[["a",[[["key","1"]],[["key",[2]]]]]]"#)
+                .unwrap_err();
+        assert_eq!(error.path, "a[1].key");
+        assert!(error.to_string().contains(", in a[1].key"));
+
+        let error = from_str::<Everything>(r#"[["list",[1,2,"three"]]]"#).unwrap_err();
+        assert_eq!(error.path, "list[2]");
+        assert_eq!(error.column, 15);
+    }
+
+    #[test]
+    fn it_rejects_unknown_and_duplicate_keys_in_strict_mode() {
+        let strict = ParseOptions::strict();
+
+        let input = r#"[["key","1"],["extra","2"]]"#;
+        assert!(from_str::<Nested>(input).is_ok());
+        let error = from_str_with_options::<Nested>(input, &strict).unwrap_err();
+        assert_eq!(error.message, "Unknown key \"extra\", expected one of: key");
+        assert_eq!(error.column, 15);
+
+        let input = r#"[["a",[["b","1"],["b","2"]]]]"#;
+        assert!(from_str::<HashMap<String, HashMap<String, String>>>(input).is_ok());
+        let error =
+            from_str_with_options::<HashMap<String, HashMap<String, String>>>(input, &strict)
+                .unwrap_err();
+        assert_eq!(error.message, "Duplicate key \"b\"");
+        assert_eq!(error.path, "a");
+
+        let error =
+            from_str_with_options::<Nested>(r#"[["key","extra"],["1","2"]]"#, &strict).unwrap_err();
+        assert!(error.message.starts_with("Unknown key \"1\""));
+    }
+
+    #[test]
+    fn it_deserializes_sqf_hash_maps() {
+        // toArray
+        let result: Nested = from_str(r#"[["key"],["value"]]"#).unwrap();
+        assert_eq!(
+            result,
+            Nested {
+                key: "value".into()
+            }
+        );
+
+        let result: HashMap<String, Vec<u8>> =
+            from_str(r#"[["a","b","c"],[[1],[],[2,3]]]"#).unwrap();
+        assert_eq!(
+            result,
+            HashMap::from([
+                ("a".into(), vec![1]),
+                ("b".into(), vec![]),
+                ("c".into(), vec![2, 3])
+            ])
+        );
+
+        let result: HashMap<String, String> = from_str("[[],[]]").unwrap();
+        assert!(result.is_empty());
+
+        // The format documented on Message::from_arma
+        let result: Data = from_str(r#"[["type","content"],["test",[["foo"],["bar"]]]]"#).unwrap();
+        assert_eq!(result, Data::Test(data::Test { foo: "bar".into() }));
+
+        let result: Vec<crate::Error> = from_str(
+            r#"[[["type","content"],["code","1"]],[["type","content"],["message","This is an error"]]]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                crate::Error::new(crate::ErrorType::Code, "1".into()),
+                crate::Error::new(crate::ErrorType::Message, "This is an error".into())
+            ]
+        );
+
+        // Nested HashMaps can use either form
+        let result: HashMap<String, Nested> =
+            from_str(r#"[["one","two"],[[["key","1"]],[["key"],["2"]]]]"#).unwrap();
+        assert_eq!(result["one"], Nested { key: "1".into() });
+        assert_eq!(result["two"], Nested { key: "2".into() });
+    }
+
+    #[test]
+    fn it_needs_a_value_for_each_key() {
+        let error = from_str::<HashMap<String, u8>>(r#"[["a","b","c"],[1,2]]"#).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expected a value for each key, but there are fewer values than keys"
+        );
+        assert_eq!(error.offset, 19);
+
+        let error = from_str::<HashMap<String, u8>>(r#"[["a"],[1,2]]"#).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expected \"]\" ending the values but found \",\""
+        );
+        assert_eq!(error.offset, 9);
+    }
+
+    #[test]
+    fn it_prefers_pairs_when_both_forms_are_valid() {
+        let result: HashMap<String, String> = from_str(r#"[["a","b"],["c","d"]]"#).unwrap();
+        assert_eq!(
+            result,
+            HashMap::from([("a".into(), "b".into()), ("c".into(), "d".into())])
+        );
+
+        let result: Data = from_str(r#"[["type","test"],["content",[["foo","bar"]]]]"#).unwrap();
+        assert_eq!(result, Data::Test(data::Test { foo: "bar".into() }));
+    }
+
+    #[test]
+    fn it_reports_where_the_contents_are_wrong() {
+        let error = from_str_with_options::<Data>(
+            r#"[["type","test"],["content",[["foo",1]]]]"#,
+            &ParseOptions::strict(),
+        )
+        .unwrap_err();
+        assert!(error.message.starts_with("invalid type: integer `1`"));
+        assert_eq!(error.offset, 36);
+
+        let error =
+            from_str::<Data>(r#"[["type","test"],["content",[["bar","baz"]]]]"#).unwrap_err();
+        assert_eq!(error.message, "missing field `foo`");
+        assert_eq!(error.offset, 28);
+
+        let error = from_str::<Data>(r#"[["type","unknown"]]"#).unwrap_err();
+        assert!(error.message.starts_with("unknown variant `unknown`"));
+        assert_eq!(error.offset, 9);
+
+        let error = from_str::<Data>(r#"[["type","test","extra"]]"#).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expected \"]\" ending the [key, value] pair but found \",\""
+        );
+        assert_eq!(error.offset, 15);
+
+        let error = from_str::<Data>(r#"[["type","ping"]] []"#).unwrap_err();
+        assert!(error.message.starts_with("Expected the end of input"));
+    }
+}
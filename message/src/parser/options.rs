@@ -0,0 +1,58 @@
+/// How forgiving the parser is when the input does not match what was asked for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Any hash that does not conform is an error naming where it is.
+    /// Unknown or duplicate keys are rejected and values must already be the requested type
+    Strict,
+
+    /// The parser's original behaviour. Arrays that do not convert are left as they are,
+    /// and scalars are coerced when the requested type asks for it: 1 -> "1", "1" -> 1, "true" -> true
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+
+    /// Arrays nested deeper than this are rejected before the parser recurses into them
+    pub max_depth: usize,
+
+    /// Input longer than this, in bytes, is rejected before parsing starts
+    pub max_input_length: usize,
+
+    /// Input with more values than this is rejected. Every string, number, boolean, nil and array counts as one
+    pub max_elements: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        // Far above anything esm_arma sends, but low enough that bad input cannot exhaust the stack or memory
+        ParseOptions {
+            mode: ParseMode::default(),
+            max_depth: 128,
+            max_input_length: 16 * 1024 * 1024,
+            max_elements: 1_000_000,
+        }
+    }
+}
+
+impl ParseOptions {
+    pub fn strict() -> Self {
+        ParseOptions {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        }
+    }
+
+    pub fn lenient() -> Self {
+        ParseOptions {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.mode == ParseMode::Strict
+    }
+}
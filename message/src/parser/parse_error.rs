@@ -0,0 +1,302 @@
+// How many characters of the input to show on either side of the error
+const EXCERPT_RADIUS: usize = 24;
+
+/// What went wrong, so callers can tell bad input apart from input that hit one of the parser's limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The input is not valid SQF, or does not match the requested type
+    Invalid,
+
+    /// The input is longer than ParseOptions::max_input_length
+    InputTooLong,
+
+    /// Arrays are nested deeper than ParseOptions::max_depth
+    TooDeep,
+
+    /// The input has more values than ParseOptions::max_elements
+    TooManyElements,
+
+    /// A number was rounded by Arma before it was sent, and the requested type needs its exact value
+    LostPrecision,
+}
+
+/// An error in Arma input, either in its syntax or its contents not matching the requested type.
+/// All positions refer to the original input, not any converted form of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub message: String,
+
+    /// Byte offset into the input
+    pub offset: usize,
+
+    /// 1 based line number
+    pub line: usize,
+
+    /// 1 based column, in characters
+    pub column: usize,
+
+    /// The part of the line surrounding the error
+    pub excerpt: String,
+
+    /// Where the error is in the excerpt, in characters
+    pub excerpt_column: usize,
+
+    /// Where the error is in the data, such as content.items[2]. Empty when it is at the top level
+    pub path: String,
+
+    // Errors raised through serde do not know where they happened until the deserializer places them
+    has_position: bool,
+}
+
+impl ParseError {
+    pub fn new<M: Into<String>>(input: &str, offset: usize, message: M) -> Self {
+        // An offset inside of a multi-byte character moves back to its start, slicing there would panic
+        let mut offset = offset.min(input.len());
+        while !input.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &input[..offset];
+
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = input[offset..]
+            .find('\n')
+            .map(|i| offset + i)
+            .unwrap_or(input.len());
+
+        let line = before.matches('\n').count() + 1;
+        let column = input[line_start..offset].chars().count() + 1;
+
+        // Build the excerpt from characters, so multi-byte characters are never split
+        let leading: Vec<char> = input[line_start..offset]
+            .chars()
+            .rev()
+            .take(EXCERPT_RADIUS)
+            .collect();
+        let trailing: String = input[offset..line_end]
+            .chars()
+            .take(EXCERPT_RADIUS)
+            .collect();
+
+        let mut excerpt: String = leading.iter().rev().collect();
+        let mut excerpt_column = leading.len();
+        if leading.len() < column - 1 {
+            excerpt.insert_str(0, "...");
+            excerpt_column += 3;
+        }
+
+        excerpt.push_str(&trailing);
+        if offset + trailing.len() < line_end {
+            excerpt.push_str("...");
+        }
+
+        ParseError {
+            kind: ParseErrorKind::Invalid,
+            message: message.into(),
+            offset,
+            line,
+            column,
+            excerpt,
+            excerpt_column,
+            path: String::new(),
+            has_position: true,
+        }
+    }
+
+    pub fn expected<E, F>(input: &str, offset: usize, expected: E, found: F) -> Self
+    where
+        E: std::fmt::Display,
+        F: std::fmt::Display,
+    {
+        ParseError::new(
+            input,
+            offset,
+            format!("Expected {expected} but found {found}"),
+        )
+    }
+
+    /// Gives the error a position if it does not already have one
+    pub(crate) fn at(self, input: &str, offset: usize) -> Self {
+        if self.has_position {
+            return self;
+        }
+
+        ParseError {
+            kind: self.kind,
+            path: self.path,
+            ..ParseError::new(input, offset, self.message)
+        }
+    }
+
+    pub(crate) fn with_kind(mut self, kind: ParseErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Adds the key the error happened under to the front of its path
+    pub(crate) fn in_key(self, key: &str) -> Self {
+        self.prefix_path(key)
+    }
+
+    /// Adds the array index the error happened at to the front of its path
+    pub(crate) fn in_index(self, index: usize) -> Self {
+        self.prefix_path(&format!("[{index}]"))
+    }
+
+    fn prefix_path(mut self, segment: &str) -> Self {
+        self.path = if self.path.is_empty() || self.path.starts_with('[') {
+            format!("{segment}{}", self.path)
+        } else {
+            format!("{segment}.{}", self.path)
+        };
+
+        self
+    }
+}
+
+impl serde::de::Error for ParseError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        ParseError {
+            kind: ParseErrorKind::Invalid,
+            message: message.to_string(),
+            offset: 0,
+            line: 0,
+            column: 0,
+            excerpt: String::new(),
+            excerpt_column: 0,
+            path: String::new(),
+            has_position: false,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if self.has_position {
+            write!(
+                f,
+                " at line {}, column {} (byte {})",
+                self.line, self.column, self.offset
+            )?;
+        }
+
+        if !self.path.is_empty() {
+            write!(f, ", in {}", self.path)?;
+        }
+
+        if self.has_position {
+            write!(
+                f,
+                "\n    {}\n    {}^",
+                self.excerpt,
+                " ".repeat(self.excerpt_column)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_points_to_the_error() {
+        let input = r#"[["type","test"],["content",[["foo",bar]]]]"#;
+        let error = ParseError::expected(input, 36, "a value", "\"bar\"");
+
+        assert_eq!(error.line, 1);
+        assert_eq!(error.column, 37);
+        assert_eq!(error.excerpt, r#"...st"],["content",[["foo",bar]]]]"#);
+        assert_eq!(
+            error.to_string(),
+            [
+                "Expected a value but found \"bar\" at line 1, column 37 (byte 36)",
+                r#"    ...st"],["content",[["foo",bar]]]]"#,
+                "                               ^"
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn it_handles_lines_and_multibyte_characters() {
+        let input = "[\n  \"ü\",\n  oops\n]";
+        let error = ParseError::new(input, 12, "Unexpected \"oops\"");
+
+        assert_eq!(error.line, 3);
+        assert_eq!(error.column, 3);
+        assert_eq!(error.excerpt, "  oops");
+        assert_eq!(error.excerpt_column, 2);
+
+        let error = ParseError::new(input, 7, "Unexpected quote");
+        assert_eq!(error.line, 2);
+        assert_eq!(error.column, 5);
+
+        let error = ParseError::new(input, input.len(), "Unexpected end of input");
+        assert_eq!(error.line, 4);
+        assert_eq!(error.column, 2);
+        assert_eq!(error.excerpt, "]");
+    }
+
+    #[test]
+    fn it_moves_offsets_inside_a_character_to_its_start() {
+        // é is 2 bytes, so byte 3 is in the middle of the first one
+        let input = "\"aéééé\"";
+        let error = ParseError::new(input, 3, "Too long");
+        assert_eq!(error.offset, 2);
+        assert_eq!(error.column, 3);
+        assert_eq!(error.excerpt, input);
+
+        let options = crate::parser::ParseOptions {
+            max_input_length: 3,
+            ..Default::default()
+        };
+
+        let error = crate::parser::from_str_with_options::<String>(input, &options).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InputTooLong);
+        assert_eq!(error.offset, 2);
+    }
+
+    #[test]
+    fn it_places_serde_errors() {
+        let input = r#"[["foo",1]]"#;
+        let error = <ParseError as serde::de::Error>::custom("invalid type");
+        assert_eq!(error.to_string(), "invalid type");
+
+        let error = error.at(input, 8);
+        assert_eq!(error.column, 9);
+        assert_eq!(
+            error.to_string(),
+            "invalid type at line 1, column 9 (byte 8)\n    [[\"foo\",1]]\n            ^"
+        );
+
+        // The innermost position wins
+        let error = error.at(input, 0);
+        assert_eq!(error.offset, 8);
+    }
+
+    #[test]
+    fn it_builds_the_path_from_the_inside_out() {
+        let error = <ParseError as serde::de::Error>::custom("invalid type")
+            .in_key("foo")
+            .in_index(2)
+            .in_key("items")
+            .in_key("content");
+
+        assert_eq!(error.path, "content.items[2].foo");
+        assert_eq!(error.to_string(), "invalid type, in content.items[2].foo");
+
+        let error = error.at("[]", 1);
+        assert_eq!(error.path, "content.items[2].foo");
+        assert!(error
+            .to_string()
+            .starts_with("invalid type at line 1, column 2 (byte 1), in content.items[2].foo\n"));
+    }
+}
@@ -0,0 +1,481 @@
+use arma_rs::Value as ArmaValue;
+use serde::ser::{self, Serialize};
+
+use super::common::MAX_EXACT_INTEGER;
+
+/// Converts any serializable value into Arma's format, respecting serde attributes.
+/// Structs and maps become arrays of key/value pairs -> [["key", value], ["key", value]]
+/// Enum variants with data become [["variant", value]], matching what parser::from_str reads
+pub fn to_arma_value<T: Serialize + ?Sized>(value: &T) -> Result<ArmaValue, SerializeError> {
+    value.serialize(Serializer)
+}
+
+/// Same as to_arma_value, but returns the SQF text for the value
+pub fn to_arma_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    to_arma_value(value).map(|v| v.to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializeError(pub String);
+
+impl std::fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl ser::Error for SerializeError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        SerializeError(message.to_string())
+    }
+}
+
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    type SerializeSeq = Elements;
+    type SerializeTuple = Elements;
+    type SerializeTupleStruct = Elements;
+    type SerializeTupleVariant = Variant<Elements>;
+    type SerializeMap = Pairs;
+    type SerializeStruct = Pairs;
+    type SerializeStructVariant = Variant<Pairs>;
+
+    fn serialize_bool(self, v: bool) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    // Arma only has 32 bit floats, use NumberString for anything that needs to be exact.
+    // Past 2^53, not even an f64 holds every integer. Those are written as their digits, the same as a NumberString
+    fn serialize_i64(self, v: i64) -> Result<ArmaValue, SerializeError> {
+        if v.unsigned_abs() > MAX_EXACT_INTEGER as u64 {
+            return Ok(ArmaValue::String(v.to_string()));
+        }
+
+        Ok(ArmaValue::Number(v as f64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<ArmaValue, SerializeError> {
+        if v > MAX_EXACT_INTEGER as u64 {
+            return Ok(ArmaValue::String(v.to_string()));
+        }
+
+        Ok(ArmaValue::Number(v as f64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::String(v.to_string()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Array(
+            v.iter().map(|b| ArmaValue::Number((*b).into())).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<ArmaValue, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<ArmaValue, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<ArmaValue, SerializeError> {
+        Ok(variant_pair(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Elements, SerializeError> {
+        Ok(Elements(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Elements, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Elements, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Variant<Elements>, SerializeError> {
+        Ok(Variant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Pairs, SerializeError> {
+        Ok(Pairs {
+            pairs: Vec::with_capacity(len.unwrap_or_default()),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Pairs, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Variant<Pairs>, SerializeError> {
+        Ok(Variant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+fn variant_pair(variant: &str, value: ArmaValue) -> ArmaValue {
+    ArmaValue::Array(vec![ArmaValue::Array(vec![
+        ArmaValue::String(variant.to_string()),
+        value,
+    ])])
+}
+
+pub struct Elements(Vec<ArmaValue>);
+
+impl ser::SerializeSeq for Elements {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.0.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Array(self.0))
+    }
+}
+
+impl ser::SerializeTuple for Elements {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Elements {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct Pairs {
+    pairs: Vec<ArmaValue>,
+    next_key: Option<ArmaValue>,
+}
+
+impl ser::SerializeMap for Pairs {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.next_key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        let Some(key) = self.next_key.take() else {
+            return Err(SerializeError(
+                "serialize_value called before serialize_key".into(),
+            ));
+        };
+
+        self.pairs
+            .push(ArmaValue::Array(vec![key, value.serialize(Serializer)?]));
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        Ok(ArmaValue::Array(self.pairs))
+    }
+}
+
+impl ser::SerializeStruct for Pairs {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.pairs.push(ArmaValue::Array(vec![
+            ArmaValue::String(key.to_string()),
+            value.serialize(Serializer)?,
+        ]));
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+pub struct Variant<T> {
+    variant: &'static str,
+    inner: T,
+}
+
+impl ser::SerializeTupleVariant for Variant<Elements> {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        Ok(variant_pair(
+            self.variant,
+            ser::SerializeSeq::end(self.inner)?,
+        ))
+    }
+}
+
+impl ser::SerializeStructVariant for Variant<Pairs> {
+    type Ok = ArmaValue;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<ArmaValue, SerializeError> {
+        Ok(variant_pair(
+            self.variant,
+            ser::SerializeMap::end(self.inner)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Data, PostInit, Test};
+    use crate::parser::from_str;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind {
+        Unit,
+        Newtype(String),
+        Tuple(u8, u8),
+        Struct { foo: String },
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Everything {
+        #[serde(rename = "renamed")]
+        string: String,
+        number: i32,
+        optional: Option<String>,
+        list: Vec<bool>,
+        kinds: Vec<Kind>,
+    }
+
+    #[test]
+    fn it_serializes_to_arma() {
+        let value = Everything {
+            string: "a \"quoted\" string".into(),
+            number: 5,
+            optional: None,
+            list: vec![true, false],
+            kinds: vec![
+                Kind::Unit,
+                Kind::Newtype("value".into()),
+                Kind::Tuple(1, 2),
+                Kind::Struct { foo: "bar".into() },
+            ],
+        };
+
+        let arma = to_arma_string(&value).unwrap();
+        assert_eq!(
+            arma,
+            r#"[["renamed","a ""quoted"" string"],["number",5],["optional",null],["list",[true,false]],["kinds",["unit",[["newtype","value"]],[["tuple",[1,2]]],[["struct",[["foo","bar"]]]]]]]"#
+        );
+
+        assert_eq!(from_str::<Everything>(&arma).unwrap(), value);
+    }
+
+    #[test]
+    fn it_writes_integers_a_float_can_not_hold_as_digits() {
+        let exact = 2_u64.pow(53);
+        assert_eq!(
+            to_arma_value(&exact).unwrap(),
+            ArmaValue::Number(exact as f64)
+        );
+        assert_eq!(
+            to_arma_value(&(exact + 1)).unwrap(),
+            ArmaValue::String("9007199254740993".into())
+        );
+        assert_eq!(
+            to_arma_value(&i64::MIN).unwrap(),
+            ArmaValue::String("-9223372036854775808".into())
+        );
+
+        let arma = to_arma_string(&u64::MAX).unwrap();
+        assert_eq!(from_str::<u64>(&arma).unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn it_serializes_data_with_its_type() {
+        let data = Data::Test(Test { foo: "bar".into() });
+        let arma = to_arma_string(&data).unwrap();
+
+        assert_eq!(arma, r#"[["type","test"],["content",[["foo","bar"]]]]"#);
+        assert_eq!(from_str::<Data>(&arma).unwrap(), data);
+
+        assert_eq!(to_arma_string(&Data::Ping).unwrap(), r#"[["type","ping"]]"#);
+    }
+
+    #[test]
+    fn it_respects_serde_renames() {
+        let arma = to_arma_value(&PostInit {
+            build_number: "1".into(),
+            community_id: "esm".into(),
+            extdb_path: String::new(),
+            extdb_version: 3,
+            gambling_modifier: 1.into(),
+            gambling_payout_base: 95.into(),
+            gambling_payout_randomizer_max: 1.into(),
+            gambling_payout_randomizer_mid: "0.5".parse().unwrap(),
+            gambling_payout_randomizer_min: 0.into(),
+            gambling_win_percentage: 35.into(),
+            logging_add_player_to_territory: true,
+            logging_demote_player: true,
+            logging_exec: true,
+            logging_gamble: false,
+            logging_modify_player: true,
+            logging_pay_territory: true,
+            logging_promote_player: true,
+            logging_remove_player_from_territory: true,
+            logging_reward_player: true,
+            logging_transfer_poptabs: true,
+            logging_upgrade_territory: true,
+            logging_channel_id: "1".into(),
+            server_id: "esm_malden".into(),
+            taxes_territory_payment: 0.into(),
+            taxes_territory_upgrade: 0.into(),
+            territory_admin_uids: vec!["1".into()],
+            version: "2.0.0".into(),
+        })
+        .unwrap()
+        .to_string();
+
+        assert!(arma.starts_with(r#"[["ESM_BuildNumber","1"],["ESM_CommunityID","esm"],["extdb_path",""],["ESM_ExtDBVersion",3]"#));
+    }
+}
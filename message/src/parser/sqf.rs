@@ -0,0 +1,715 @@
+use std::borrow::Cow;
+
+use serde_json::{Number, Value as JSONValue};
+
+use super::{ParseError, ParseErrorKind, ParseOptions};
+
+/*
+    Lexer and parser for SQF array literals, as produced by `str` in Arma.
+
+    value  := array | string | number | boolean | null
+    array  := "[" (value ("," value)*)? "]"
+    string := '"' (any character, with "" as an escaped ") '"'
+            | "'" (any character, with '' as an escaped ') "'"
+    null   := nil | any | null | <null> | <NULL-object> (and the other <NULL-*> values)
+
+    Whitespace and SQF comments, both // line and block comments, are skipped between tokens
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token<'a> {
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    String(Cow<'a, str>),
+    Number(&'a str),
+    Word(&'a str),
+    End,
+}
+
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::OpenBracket => write!(f, "\"[\""),
+            Token::CloseBracket => write!(f, "\"]\""),
+            Token::Comma => write!(f, "\",\""),
+            Token::String(_) => write!(f, "a string"),
+            Token::Number(n) => write!(f, "number {n}"),
+            Token::Word(w) => write!(f, "{w:?}"),
+            Token::End => write!(f, "the end of input"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Lexer<'a> {
+    input: &'a str,
+    position: usize,
+    peeked: Option<(usize, Token<'a>)>,
+    depth: usize,
+    elements: usize,
+    max_depth: usize,
+    max_elements: usize,
+}
+
+impl<'a> Lexer<'a> {
+    #[cfg(test)]
+    pub fn new(input: &'a str) -> Self {
+        Lexer::with_options(input, &ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: &ParseOptions) -> Self {
+        Lexer {
+            input,
+            position: 0,
+            peeked: None,
+            depth: 0,
+            elements: 0,
+            max_depth: options.max_depth,
+            max_elements: options.max_elements,
+        }
+    }
+
+    /// Returns the byte offset of the token and the token itself
+    pub fn next_token(&mut self) -> Result<(usize, Token<'a>), ParseError> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+
+        self.lex()
+    }
+
+    pub fn peek_token(&mut self) -> Result<&(usize, Token<'a>), ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lex()?);
+        }
+
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    fn lex(&mut self) -> Result<(usize, Token<'a>), ParseError> {
+        let bytes = self.input.as_bytes();
+
+        self.skip_whitespace()?;
+
+        let start = self.position;
+        let Some(byte) = bytes.get(start) else {
+            return Ok((start, Token::End));
+        };
+
+        let token = match byte {
+            b'[' => {
+                self.position += 1;
+                Token::OpenBracket
+            }
+            b']' => {
+                self.position += 1;
+                Token::CloseBracket
+            }
+            b',' => {
+                self.position += 1;
+                Token::Comma
+            }
+            b'"' | b'\'' => self.lex_string(*byte)?,
+            b'0'..=b'9' | b'-' | b'+' | b'.' => {
+                self.position += bytes[start..]
+                    .iter()
+                    .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                    .unwrap_or(bytes.len() - start);
+
+                // Infinity and NaN -> 1.#INF, -1.#IND, 1.#QNAN
+                if bytes.get(self.position) == Some(&b'#') {
+                    self.position += 1 + bytes[self.position + 1..]
+                        .iter()
+                        .position(|b| !b.is_ascii_alphanumeric())
+                        .unwrap_or(bytes.len() - self.position - 1);
+                }
+
+                Token::Number(&self.input[start..self.position])
+            }
+            b'<' => {
+                let Some(length) = self.input[start..].find('>') else {
+                    return Err(self.error(self.input.len(), "\">\"", "the end of input"));
+                };
+
+                self.position += length + 1;
+                Token::Word(&self.input[start..self.position])
+            }
+            b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
+                self.position += bytes[start..]
+                    .iter()
+                    .position(|b| !(b.is_ascii_alphanumeric() || *b == b'_'))
+                    .unwrap_or(bytes.len() - start);
+
+                Token::Word(&self.input[start..self.position])
+            }
+            _ => {
+                let character = self.input[start..].chars().next().unwrap_or_default();
+                return Err(self.error(start, "a value", format!("{character:?}")));
+            }
+        };
+
+        self.enforce_limits(start, &token)?;
+        Ok((start, token))
+    }
+
+    // Spaces, newlines and SQF comments may appear anywhere outside of a string
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        let bytes = self.input.as_bytes();
+
+        loop {
+            while self.position < bytes.len() && bytes[self.position].is_ascii_whitespace() {
+                self.position += 1;
+            }
+
+            let rest = &self.input[self.position..];
+            if rest.starts_with("//") {
+                self.position += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let Some(length) = comment.find("*/") else {
+                    return Err(self.error(
+                        self.position,
+                        "a closing \"*/\" for this comment",
+                        "the end of input",
+                    ));
+                };
+
+                self.position += length + 4;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // Checked as each token is read, so nothing ever recurses past the depth limit
+    fn enforce_limits(&mut self, offset: usize, token: &Token) -> Result<(), ParseError> {
+        match token {
+            Token::OpenBracket => {
+                self.depth += 1;
+                if self.depth > self.max_depth {
+                    return Err(ParseError::new(
+                        self.input,
+                        offset,
+                        format!(
+                            "Arrays are nested deeper than the limit of {}",
+                            self.max_depth
+                        ),
+                    )
+                    .with_kind(ParseErrorKind::TooDeep));
+                }
+            }
+            Token::CloseBracket => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+
+        if matches!(
+            token,
+            Token::OpenBracket | Token::String(_) | Token::Number(_) | Token::Word(_)
+        ) {
+            self.elements += 1;
+            if self.elements > self.max_elements {
+                return Err(ParseError::new(
+                    self.input,
+                    offset,
+                    format!(
+                        "Input has more values than the limit of {}",
+                        self.max_elements
+                    ),
+                )
+                .with_kind(ParseErrorKind::TooManyElements));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    pub fn number_error(&self, offset: usize, text: &str) -> ParseError {
+        if text.contains('#') {
+            return ParseError::new(
+                self.input,
+                offset,
+                format!("{text:?} is infinity or NaN in Arma, which cannot be represented"),
+            );
+        }
+
+        self.error(offset, "a number", format!("{text:?}"))
+    }
+
+    pub fn error<E, F>(&self, offset: usize, expected: E, found: F) -> ParseError
+    where
+        E: std::fmt::Display,
+        F: std::fmt::Display,
+    {
+        ParseError::expected(self.input, offset, expected, found)
+    }
+
+    // SQF escapes a quote inside of a string by doubling it -> "a ""quoted"" word"
+    fn lex_string(&mut self, quote: u8) -> Result<Token<'a>, ParseError> {
+        let bytes = self.input.as_bytes();
+        let start = self.position;
+        let content_start = start + 1;
+
+        let mut escaped = false;
+        let mut index = content_start;
+        loop {
+            match bytes[index..].iter().position(|b| *b == quote) {
+                Some(offset) => index += offset,
+                None => {
+                    return Err(self.error(
+                        start,
+                        format!("a closing {:?} for this string", quote as char),
+                        "the end of input",
+                    ))
+                }
+            }
+
+            // Doubled quote, keep going
+            if bytes.get(index + 1) == Some(&quote) {
+                escaped = true;
+                index += 2;
+                continue;
+            }
+
+            break;
+        }
+
+        self.position = index + 1;
+
+        let content = &self.input[content_start..index];
+        if !escaped {
+            return Ok(Token::String(Cow::Borrowed(content)));
+        }
+
+        // Drop every other quote in a series
+        let mut unescaped = String::with_capacity(content.len());
+        let mut kept_quote = false;
+        for character in content.chars() {
+            if character == quote as char {
+                kept_quote = !kept_quote;
+                if !kept_quote {
+                    continue;
+                }
+            }
+
+            unescaped.push(character);
+        }
+
+        Ok(Token::String(Cow::Owned(unescaped)))
+    }
+}
+
+/// Rejects input over the length limit before any of it is read
+pub(crate) fn check_input_length(input: &str, options: &ParseOptions) -> Result<(), ParseError> {
+    if input.len() <= options.max_input_length {
+        return Ok(());
+    }
+
+    Err(ParseError::new(
+        input,
+        options.max_input_length,
+        format!(
+            "Input is {} bytes, which is over the limit of {}",
+            input.len(),
+            options.max_input_length
+        ),
+    )
+    .with_kind(ParseErrorKind::InputTooLong))
+}
+
+/// Parses SQF array text into JSON. Key/value pair arrays are left as arrays, see parser::validate_content
+pub(crate) fn parse(input: &str, options: &ParseOptions) -> Result<JSONValue, ParseError> {
+    check_input_length(input, options)?;
+
+    let mut lexer = Lexer::with_options(input, options);
+    let value = parse_value(&mut lexer)?;
+
+    match lexer.next_token()? {
+        (_, Token::End) => Ok(value),
+        (position, token) => Err(lexer.error(position, "the end of input", token)),
+    }
+}
+
+pub(crate) fn parse_value(lexer: &mut Lexer) -> Result<JSONValue, ParseError> {
+    let (position, token) = lexer.next_token()?;
+
+    match token {
+        Token::OpenBracket => parse_array(lexer),
+        Token::String(s) => Ok(JSONValue::String(s.into_owned())),
+        Token::Number(n) => match parse_number(n) {
+            Some(n) => Ok(JSONValue::Number(n)),
+            None => Err(lexer.number_error(position, n)),
+        },
+        Token::Word(w) => match parse_word(w) {
+            Some(v) => Ok(v),
+            None => Err(lexer.error(
+                position,
+                "a string, number, boolean, nil or array",
+                format!("{w:?}"),
+            )),
+        },
+        token => Err(lexer.error(position, "a value", token)),
+    }
+}
+
+fn parse_array(lexer: &mut Lexer) -> Result<JSONValue, ParseError> {
+    let mut array = Vec::new();
+
+    if let (_, Token::CloseBracket) = lexer.peek_token()? {
+        lexer.next_token()?;
+        return Ok(JSONValue::Array(array));
+    }
+
+    loop {
+        array.push(parse_value(lexer)?);
+
+        match lexer.next_token()? {
+            (_, Token::Comma) => continue,
+            (_, Token::CloseBracket) => return Ok(JSONValue::Array(array)),
+            (position, token) => return Err(lexer.error(position, "\",\" or \"]\"", token)),
+        }
+    }
+}
+
+/// Returns the text of each element of the top level array, exactly as it is written in the input
+pub(crate) fn split_elements<'a>(
+    input: &'a str,
+    options: &ParseOptions,
+) -> Result<Vec<&'a str>, ParseError> {
+    check_input_length(input, options)?;
+
+    let mut lexer = Lexer::with_options(input, options);
+    match lexer.next_token()? {
+        (_, Token::OpenBracket) => {}
+        (position, token) => return Err(lexer.error(position, "\"[\"", token)),
+    }
+
+    let mut elements = Vec::new();
+    if let (_, Token::CloseBracket) = lexer.peek_token()? {
+        lexer.next_token()?;
+    } else {
+        loop {
+            let start = lexer.peek_token()?.0;
+            skip_value(&mut lexer)?;
+            elements.push(&input[start..lexer.position]);
+
+            match lexer.next_token()? {
+                (_, Token::Comma) => continue,
+                (_, Token::CloseBracket) => break,
+                (position, token) => return Err(lexer.error(position, "\",\" or \"]\"", token)),
+            }
+        }
+    }
+
+    match lexer.next_token()? {
+        (_, Token::End) => Ok(elements),
+        (position, token) => Err(lexer.error(position, "the end of input", token)),
+    }
+}
+
+/// Moves past the next value without building it. Used to look ahead
+pub(crate) fn skip_value(lexer: &mut Lexer) -> Result<(), ParseError> {
+    let mut depth = 0_usize;
+
+    loop {
+        let (position, token) = lexer.next_token()?;
+
+        match token {
+            Token::OpenBracket => depth += 1,
+            Token::CloseBracket if depth > 0 => depth -= 1,
+            Token::Comma if depth > 0 => {}
+            Token::String(_) | Token::Number(_) | Token::Word(_) => {}
+            token => return Err(lexer.error(position, "a value", token)),
+        }
+
+        if depth == 0 {
+            return Ok(());
+        }
+    }
+}
+
+/*
+    Arma floats are 32 bit and `str` only prints 6 significant digits, switching to scientific notation at 1e+006.
+    Any whole number written that way has lost its last digits: 1234567890 becomes 1.23457e+009.
+    That is the case when the exponent moves the decimal point past the last digit given
+*/
+pub(crate) fn has_lost_precision(text: &str) -> bool {
+    let Some((mantissa, exponent)) = text.trim().split_once(['e', 'E']) else {
+        return false;
+    };
+
+    let Ok(exponent) = exponent.parse::<i64>() else {
+        return false;
+    };
+
+    let mantissa = mantissa.trim_start_matches(['-', '+']);
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if !(integer.bytes().chain(fraction.bytes())).any(|b| matches!(b, b'1'..=b'9')) {
+        return false;
+    }
+
+    exponent > fraction.trim_end_matches('0').len() as i64
+}
+
+pub(crate) fn parse_number(input: &str) -> Option<Number> {
+    if let Ok(n) = input.parse::<i64>() {
+        return Some(Number::from(n));
+    }
+
+    if let Ok(n) = input.parse::<u64>() {
+        return Some(Number::from(n));
+    }
+
+    input.parse::<f64>().ok().and_then(Number::from_f64)
+}
+
+pub(crate) fn parse_word(input: &str) -> Option<JSONValue> {
+    match input {
+        "true" => Some(JSONValue::Bool(true)),
+        "false" => Some(JSONValue::Bool(false)),
+        "nil" | "any" | "null" => Some(JSONValue::Null),
+        w if w.eq_ignore_ascii_case("<null>") => Some(JSONValue::Null),
+        w if w.starts_with("<NULL-") => Some(JSONValue::Null),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(input: &str) -> Result<JSONValue, ParseError> {
+        super::parse(input, &ParseOptions::default())
+    }
+
+    #[test]
+    fn it_tokenizes() {
+        let mut lexer = Lexer::new(r#"[ "a""b", 'c''d',-1.5e+009,nil,<null>]"#);
+
+        let mut tokens = vec![];
+        loop {
+            let (position, token) = lexer.next_token().unwrap();
+            if token == Token::End {
+                break;
+            }
+
+            tokens.push((position, token));
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                (0, Token::OpenBracket),
+                (2, Token::String(Cow::Borrowed("a\"b"))),
+                (8, Token::Comma),
+                (10, Token::String(Cow::Borrowed("c'd"))),
+                (16, Token::Comma),
+                (17, Token::Number("-1.5e+009")),
+                (26, Token::Comma),
+                (27, Token::Word("nil")),
+                (30, Token::Comma),
+                (31, Token::Word("<null>")),
+                (37, Token::CloseBracket),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_values() {
+        assert_eq!(parse("[]").unwrap(), json!([]));
+        assert_eq!(parse("\"\"").unwrap(), json!(""));
+        assert_eq!(parse("\"\"\"\"").unwrap(), json!("\""));
+        assert_eq!(parse("5").unwrap(), json!(5));
+        assert_eq!(parse("-5.5").unwrap(), json!(-5.5));
+        assert_eq!(parse("1e+009").unwrap(), json!(1e9));
+        assert_eq!(parse("true").unwrap(), json!(true));
+        assert_eq!(parse("<NULL-object>").unwrap(), json!(null));
+
+        assert_eq!(
+            parse(r#"[1,"two",[true,false,[nil,any,null,<null>]],"[""a"",1]",'it''s']"#).unwrap(),
+            json!([
+                1,
+                "two",
+                [true, false, [null, null, null, null]],
+                "[\"a\",1]",
+                "it's"
+            ])
+        );
+
+        // Escape characters mean nothing in SQF
+        assert_eq!(parse(r#""\\ \n \""""#).unwrap(), json!("\\\\ \\n \\\""));
+    }
+
+    #[test]
+    fn it_reports_where_parsing_failed() {
+        let error = parse(r#"[["foo","bar"],["baz" 1]]"#).unwrap_err();
+        assert_eq!(error.offset, 22);
+        assert_eq!(error.message, "Expected \",\" or \"]\" but found number 1");
+
+        let error = parse("[1,]").unwrap_err();
+        assert_eq!(error.offset, 3);
+        assert_eq!(error.message, "Expected a value but found \"]\"");
+
+        let error = parse("[\"foo\",\n\"bar]").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+        assert!(error.message.ends_with("but found the end of input"));
+
+        let error = parse("[true,undefined]").unwrap_err();
+        assert_eq!(error.offset, 6);
+        assert!(error.message.ends_with("but found \"undefined\""));
+
+        let error = parse("[1]]").unwrap_err();
+        assert_eq!(error.offset, 3);
+        assert!(error.message.starts_with("Expected the end of input"));
+    }
+
+    #[test]
+    fn it_rejects_invalid_input() {
+        assert!(parse("").is_err());
+        assert!(parse("[").is_err());
+        assert!(parse("[1,]").is_err());
+        assert!(parse("[1 2]").is_err());
+        assert!(parse("[1]]").is_err());
+        assert!(parse("\"unterminated").is_err());
+        assert!(parse("[undefined]").is_err());
+        assert!(parse("[1.2.3]").is_err());
+    }
+
+    #[test]
+    fn it_splits_the_top_level_array() {
+        let options = ParseOptions::default();
+        let input = r#"[ "a""b" , [["c", [1, 2]]],-1.5e+009,nil ]"#;
+        assert_eq!(
+            split_elements(input, &options).unwrap(),
+            vec![r#""a""b""#, r#"[["c", [1, 2]]]"#, "-1.5e+009", "nil"]
+        );
+
+        assert!(split_elements("[]", &options).unwrap().is_empty());
+
+        let error = split_elements("\"a\"", &options).unwrap_err();
+        assert_eq!(error.message, "Expected \"[\" but found a string");
+
+        let error = split_elements("[1] 2", &options).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Expected the end of input but found number 2"
+        );
+    }
+
+    #[test]
+    fn it_skips_whitespace_and_comments() {
+        let input = r#"
+            // A hand written message
+            [
+                ["type", "test"], /* the content, which
+                                     spans a few lines */
+                ["content", [["foo", "a // not a comment"], ['bar', '/* or this */']]]
+            ] // trailing comment"#;
+
+        assert_eq!(
+            parse(input).unwrap(),
+            json!([
+                ["type", "test"],
+                [
+                    "content",
+                    [["foo", "a // not a comment"], ["bar", "/* or this */"]]
+                ]
+            ])
+        );
+
+        assert_eq!(parse("[\t1,\r\n2/**/,3//]\n]").unwrap(), json!([1, 2, 3]));
+
+        let options = ParseOptions::default();
+        assert_eq!(
+            split_elements("[ /* id */ \"a\" , [1, /* two */ 2] // end\n]", &options).unwrap(),
+            vec![r#""a""#, "[1, /* two */ 2]"]
+        );
+
+        let error = parse("[1, /* never closed").unwrap_err();
+        assert_eq!(error.offset, 4);
+        assert_eq!(
+            error.message,
+            "Expected a closing \"*/\" for this comment but found the end of input"
+        );
+
+        assert!(parse("[1 / 2]").is_err());
+    }
+
+    #[test]
+    fn it_detects_lost_precision() {
+        for text in [
+            "1.23457e+009",
+            "7.65612e+016",
+            "1e+006",
+            "-1.5e+007",
+            "1.5E3",
+            "0.05e+003",
+        ] {
+            assert!(has_lost_precision(text), "{text}");
+        }
+
+        for text in [
+            "1234567890",
+            "1.5",
+            "1.5e+001",
+            "1.25e-005",
+            "0e+009",
+            "1e+00x",
+        ] {
+            assert!(!has_lost_precision(text), "{text}");
+        }
+    }
+
+    #[test]
+    fn it_explains_infinity_and_nan() {
+        let error = parse("[1,1.#INF]").unwrap_err();
+        assert_eq!(
+            error.message,
+            "\"1.#INF\" is infinity or NaN in Arma, which cannot be represented"
+        );
+        assert_eq!(error.offset, 3);
+
+        let error = parse("-1.#IND").unwrap_err();
+        assert!(error.message.starts_with("\"-1.#IND\" is infinity or NaN"));
+    }
+
+    #[test]
+    fn it_enforces_limits_while_lexing() {
+        let options = ParseOptions {
+            max_depth: 3,
+            max_elements: 7,
+            ..Default::default()
+        };
+
+        let mut lexer = Lexer::with_options("[[[1]],[[2]]]", &options);
+        assert_eq!(parse_value(&mut lexer).unwrap(), json!([[[1]], [[2]]]));
+
+        let mut lexer = Lexer::with_options("[[[[1]]]]", &options);
+        let error = parse_value(&mut lexer).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::TooDeep);
+        assert_eq!(error.offset, 3);
+
+        let mut lexer = Lexer::with_options("[1,2,3,4,5,6,7]", &options);
+        let error = parse_value(&mut lexer).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::TooManyElements);
+        assert_eq!(error.offset, 13);
+
+        let error = check_input_length(
+            "[1,2]",
+            &ParseOptions {
+                max_input_length: 4,
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::InputTooLong);
+        assert_eq!(
+            error.message,
+            "Input is 5 bytes, which is over the limit of 4"
+        );
+    }
+}
@@ -0,0 +1,765 @@
+use std::collections::HashSet;
+
+use arma_rs::Value as ArmaValue;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use super::common::{self, deserialize_coerced, Coerced, Found, Scalar};
+use super::{sqf, ParseError, ParseErrorKind, ParseOptions, HASH_MARKER};
+use crate::number_string::NUMBER_STRING;
+
+// Arma's str writes numbers this large with an exponent and only 6 significant digits, so they may have been rounded
+const MIN_EXPONENT_NUMBER: f64 = 1e6;
+
+/// Deserializes a value arma-rs has already parsed into `T`, without going back through text.
+/// Follows the same rules as from_str. arma-rs has already read numbers as floats, so each one is checked as the
+/// text Arma would have written for it: a whole number of a million or more is an error for an integer or NumberString
+pub fn from_value<T: DeserializeOwned>(value: &ArmaValue) -> Result<T, ParseError> {
+    from_value_with_options(value, &ParseOptions::default())
+}
+
+/// Same as from_value, with control over how strictly the value has to match `T`
+pub fn from_value_with_options<T: DeserializeOwned>(
+    value: &ArmaValue,
+    options: &ParseOptions,
+) -> Result<T, ParseError> {
+    T::deserialize(ValueDeserializer {
+        value,
+        options,
+        depth: 0,
+    })
+}
+
+#[derive(Clone, Copy)]
+struct ValueDeserializer<'a> {
+    value: &'a ArmaValue,
+    options: &'a ParseOptions,
+    depth: usize,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn child(&self, value: &'a ArmaValue) -> ValueDeserializer<'a> {
+        ValueDeserializer {
+            value,
+            options: self.options,
+            depth: self.depth + 1,
+        }
+    }
+
+    // Stands in for the lexer's limit, as the value is already in memory but recursing into it is not free
+    fn array(&self, expected: &str) -> Result<&'a [ArmaValue], ParseError> {
+        let ArmaValue::Array(array) = self.value else {
+            return Err(expected_error(expected, self.value));
+        };
+
+        if self.depth >= self.options.max_depth {
+            return Err(<ParseError as de::Error>::custom(format!(
+                "Arrays are nested deeper than the limit of {}",
+                self.options.max_depth
+            ))
+            .with_kind(ParseErrorKind::TooDeep));
+        }
+
+        Ok(array)
+    }
+
+    fn read_map<'de, V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let array = self.array("an array of key/value pairs")?;
+
+        if let Some(pairs) = strip_hash_marker(array) {
+            return visitor.visit_map(Pairs::new(self, pairs, fields));
+        }
+
+        // SQF HashMap converted with toArray
+        if let Some((keys, values)) = keys_and_values(array, fields) {
+            if keys.len() != values.len() {
+                return Err(<ParseError as de::Error>::custom(format!(
+                    "Expected {} values, one for each key, but found {}",
+                    keys.len(),
+                    values.len()
+                )));
+            }
+
+            return visitor.visit_map(KeysValues {
+                de: self,
+                entries: keys.iter().zip(values),
+                fields,
+                seen: HashSet::new(),
+                key: None,
+            });
+        }
+
+        visitor.visit_map(Pairs::new(self, array, fields))
+    }
+
+    fn check_key(
+        &self,
+        key: &str,
+        fields: &[&str],
+        seen: &mut HashSet<String>,
+    ) -> Result<(), ParseError> {
+        common::check_key(self.options, key, fields, seen)
+            .map_err(<ParseError as de::Error>::custom)
+    }
+
+    // The same coercions as from_str, with the number written back out as text for them to check
+    fn deserialize_coerced<'de, V: Visitor<'de>>(
+        self,
+        target: Scalar,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let text;
+        let found = match self.value {
+            ArmaValue::Number(n) => {
+                text = float_to_text(*n);
+                Found::Number(&text)
+            }
+            ArmaValue::String(s) => Found::String(s),
+            ArmaValue::Boolean(b) => Found::Bool(*b),
+            _ => return de::Deserializer::deserialize_any(self, visitor),
+        };
+
+        match common::coerce(target, found, self.options) {
+            Some(Coerced::LostPrecision(text)) => Err(lost_precision(&text)),
+            Some(coerced) => common::visit_coerced(coerced, visitor),
+            None => de::Deserializer::deserialize_any(self, visitor),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self.value {
+            // Without a type to guide it, an array is only read as a map if it starts with the hash marker
+            ArmaValue::Array(_) => {
+                let array = self.array("an array")?;
+                match strip_hash_marker(array) {
+                    Some(pairs) => visitor.visit_map(Pairs::new(self, pairs, &[])),
+                    None => visitor.visit_seq(Elements::new(self, array)),
+                }
+            }
+            ArmaValue::String(s) => visitor.visit_str(s),
+            ArmaValue::Number(n) => visit_float(*n, visitor),
+            ArmaValue::Boolean(b) => visitor.visit_bool(*b),
+            ArmaValue::Null => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self.value {
+            ArmaValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self.value {
+            ArmaValue::Null => visitor.visit_unit(),
+            value => Err(expected_error("nil", value)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        if name != NUMBER_STRING {
+            return visitor.visit_newtype_struct(self);
+        }
+
+        // NumberString asks by name so its number can be checked the same way from_str checks its text
+        let text = match self.value {
+            ArmaValue::Number(n) => float_to_text(*n),
+            ArmaValue::String(s) => s.clone(),
+            _ => return de::Deserializer::deserialize_any(self, visitor),
+        };
+
+        if sqf::has_lost_precision(&text) {
+            return Err(lost_precision(&text));
+        }
+
+        visitor.visit_str(&text)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let array = self.array("an array")?;
+
+        // Allows a list of pairs, such as Vec<(String, String)>, to be read from a marked hash
+        let elements = strip_hash_marker(array).unwrap_or(array);
+        visitor.visit_seq(Elements::new(self, elements))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        self.read_map(&[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.read_map(fields, visitor)
+    }
+
+    // "variant" or [["variant", value]]
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        match self.value {
+            ArmaValue::String(s) => visitor.visit_enum(
+                IntoDeserializer::<ParseError>::into_deserializer(s.to_string()),
+            ),
+            ArmaValue::Array(array) => match &array[..] {
+                [ArmaValue::Array(pair)] if pair.len() == 2 => visitor.visit_enum(Variant {
+                    de: self.child(&array[0]),
+                    variant: &pair[0],
+                    value: &pair[1],
+                }),
+                _ => Err(expected_error("a [[\"variant\", value]] array", self.value)),
+            },
+            value => Err(expected_error(
+                "a variant name or [[\"variant\", value]]",
+                value,
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_unit()
+    }
+
+    deserialize_coerced! {
+        deserialize_bool => Bool,
+        deserialize_i8 => Integer,
+        deserialize_i16 => Integer,
+        deserialize_i32 => Integer,
+        deserialize_i64 => Integer,
+        deserialize_i128 => Integer,
+        deserialize_u8 => Integer,
+        deserialize_u16 => Integer,
+        deserialize_u32 => Integer,
+        deserialize_u64 => Integer,
+        deserialize_u128 => Integer,
+        deserialize_f32 => Float,
+        deserialize_f64 => Float,
+        deserialize_str => String,
+        deserialize_string => String
+    }
+
+    forward_to_deserialize_any! {
+        char bytes byte_buf identifier
+    }
+}
+
+fn describe(value: &ArmaValue) -> String {
+    match value {
+        ArmaValue::Null => "nil".into(),
+        ArmaValue::Number(n) => format!("number {n}"),
+        ArmaValue::Array(_) => "an array".into(),
+        ArmaValue::Boolean(b) => format!("{b:?}"),
+        ArmaValue::String(_) => "a string".into(),
+    }
+}
+
+fn expected_error(expected: &str, found: &ArmaValue) -> ParseError {
+    <ParseError as de::Error>::custom(format!("Expected {expected} but found {}", describe(found)))
+}
+
+fn lost_precision(text: &str) -> ParseError {
+    <ParseError as de::Error>::custom(common::lost_precision_message(text))
+        .with_kind(ParseErrorKind::LostPrecision)
+}
+
+fn strip_hash_marker(array: &[ArmaValue]) -> Option<&[ArmaValue]> {
+    match array.first() {
+        Some(ArmaValue::String(s)) if s == HASH_MARKER => Some(&array[1..]),
+        _ => None,
+    }
+}
+
+// The [[keys], [values]] form SQF's toArray creates for a HashMap
+fn keys_and_values<'a>(
+    array: &'a [ArmaValue],
+    fields: &[&str],
+) -> Option<(Vec<&'a str>, &'a [ArmaValue])> {
+    let [ArmaValue::Array(keys), ArmaValue::Array(values)] = array else {
+        return None;
+    };
+
+    let keys = keys
+        .iter()
+        .map(|k| match k {
+            ArmaValue::String(k) => Some(k.as_str()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let first_value_is_string = matches!(values.first(), Some(ArmaValue::String(_)));
+    if !common::is_keys_and_values(&keys, first_value_is_string, fields) {
+        return None;
+    }
+
+    Some((keys, values))
+}
+
+/*
+    The whole numbers arma-rs hands over as floats are visited as integers, the same as their text would be.
+    Arma wrote the larger ones with an exponent, which from_str reads as a float, so they stay floats here as well
+*/
+fn visit_float<'de, V: Visitor<'de>>(number: f64, visitor: V) -> Result<V::Value, ParseError> {
+    if !is_written_as_integer(number) {
+        return visitor.visit_f64(number);
+    }
+
+    if number < 0.0 {
+        visitor.visit_i64(number as i64)
+    } else {
+        visitor.visit_u64(number as u64)
+    }
+}
+
+fn is_written_as_integer(number: f64) -> bool {
+    number.fract() == 0.0 && number.abs() < MIN_EXPONENT_NUMBER
+}
+
+// Large whole numbers are written with an exponent, the same as Arma does, so the lost precision check catches them
+fn float_to_text(number: f64) -> String {
+    if is_written_as_integer(number) {
+        (number as i64).to_string()
+    } else if number.is_finite() && number.fract() == 0.0 {
+        format!("{number:e}")
+    } else {
+        number.to_string()
+    }
+}
+
+struct Elements<'a> {
+    de: ValueDeserializer<'a>,
+    elements: std::iter::Enumerate<std::slice::Iter<'a, ArmaValue>>,
+}
+
+impl<'a> Elements<'a> {
+    fn new(de: ValueDeserializer<'a>, elements: &'a [ArmaValue]) -> Self {
+        Elements {
+            de,
+            elements: elements.iter().enumerate(),
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'_> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ParseError> {
+        let Some((index, element)) = self.elements.next() else {
+            return Ok(None);
+        };
+
+        seed.deserialize(self.de.child(element))
+            .map(Some)
+            .map_err(|e| e.in_index(index))
+    }
+}
+
+struct Pairs<'a> {
+    de: ValueDeserializer<'a>,
+    pairs: std::slice::Iter<'a, ArmaValue>,
+    fields: &'static [&'static str],
+    seen: HashSet<String>,
+
+    // The pair being read, so its value can be read and errors in it can name its key
+    value: Option<&'a ArmaValue>,
+    key: Option<&'a str>,
+}
+
+impl<'a> Pairs<'a> {
+    fn new(
+        de: ValueDeserializer<'a>,
+        pairs: &'a [ArmaValue],
+        fields: &'static [&'static str],
+    ) -> Self {
+        Pairs {
+            de,
+            pairs: pairs.iter(),
+            fields,
+            seen: HashSet::new(),
+            value: None,
+            key: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Pairs<'_> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ParseError> {
+        let Some(pair) = self.pairs.next() else {
+            return Ok(None);
+        };
+
+        let [key, value] = self.de.child(pair).array("a [key, value] pair")? else {
+            return Err(expected_error("a [key, value] pair", pair));
+        };
+
+        self.key = match key {
+            ArmaValue::String(key) => {
+                self.de.check_key(key, self.fields, &mut self.seen)?;
+                Some(key)
+            }
+            _ => None,
+        };
+
+        self.value = Some(value);
+        seed.deserialize(self.de.child(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ParseError> {
+        let value = self.value.take().unwrap_or(&ArmaValue::Null);
+
+        seed.deserialize(self.de.child(value))
+            .map_err(|e| match self.key {
+                Some(key) => e.in_key(key),
+                None => e,
+            })
+    }
+}
+
+struct KeysValues<'a> {
+    de: ValueDeserializer<'a>,
+    entries: std::iter::Zip<std::slice::Iter<'a, &'a str>, std::slice::Iter<'a, ArmaValue>>,
+    fields: &'static [&'static str],
+    seen: HashSet<String>,
+    key: Option<(&'a str, &'a ArmaValue)>,
+}
+
+impl<'de> de::MapAccess<'de> for KeysValues<'_> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ParseError> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+
+        self.de.check_key(key, self.fields, &mut self.seen)?;
+        self.key = Some((key, value));
+
+        seed.deserialize(IntoDeserializer::<ParseError>::into_deserializer(
+            key.to_string(),
+        ))
+        .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ParseError> {
+        let Some((key, value)) = self.key.take() else {
+            return seed.deserialize(self.de.child(&ArmaValue::Null));
+        };
+
+        seed.deserialize(self.de.child(value))
+            .map_err(|e| e.in_key(key))
+    }
+}
+
+struct Variant<'a> {
+    de: ValueDeserializer<'a>,
+    variant: &'a ArmaValue,
+    value: &'a ArmaValue,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for Variant<'a> {
+    type Error = ParseError;
+    type Variant = ValueDeserializer<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, ValueDeserializer<'a>), ParseError> {
+        let variant = seed.deserialize(self.de.child(self.variant))?;
+        Ok((variant, self.de.child(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueDeserializer<'_> {
+    type Error = ParseError;
+
+    fn unit_variant(self) -> Result<(), ParseError> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ParseError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.read_map(fields, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Data, Reward};
+    use arma_rs::IntoArma;
+    use serde::Deserialize;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Everything {
+        string: String,
+        number: i32,
+        float: f64,
+        boolean: bool,
+        optional: Option<String>,
+        missing: Option<String>,
+        list: Vec<u8>,
+        tuple: (String, i64),
+        map: HashMap<String, String>,
+        kind: Kind,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Kind {
+        Unit,
+        Newtype(String),
+    }
+
+    #[test]
+    fn it_deserializes_values() {
+        let value = json!([
+            ["string", "a \"quoted\" string"],
+            ["number", -5],
+            ["float", 1.5],
+            ["boolean", true],
+            ["optional", "here"],
+            ["list", [1, 2, 3]],
+            ["tuple", ["a", 1]],
+            ["map", ["#hash", ["b", "c"]]],
+            ["kind", "unit"]
+        ])
+        .to_arma();
+
+        assert_eq!(
+            from_value::<Everything>(&value).unwrap(),
+            Everything {
+                string: "a \"quoted\" string".into(),
+                number: -5,
+                float: 1.5,
+                boolean: true,
+                optional: Some("here".into()),
+                missing: None,
+                list: vec![1, 2, 3],
+                tuple: ("a".into(), 1),
+                map: HashMap::from([("b".into(), "c".into())]),
+                kind: Kind::Unit,
+            }
+        );
+    }
+
+    #[test]
+    fn it_follows_the_same_rules_as_text() {
+        // SQF HashMap converted with toArray
+        let value = json!([
+            ["type", "content"],
+            [
+                "reward",
+                [["locker_poptabs", "items"], [1500, [["a", "1.5e+001"]]]]
+            ]
+        ])
+        .to_arma();
+
+        let Data::Reward(reward) = from_value::<Data>(&value).unwrap() else {
+            panic!("Expected Reward");
+        };
+
+        assert_eq!(
+            reward,
+            Reward {
+                items: Some(HashMap::from([("a".into(), 15.into())])),
+                locker_poptabs: Some(1500.into()),
+                player_poptabs: None,
+                respect: None,
+                vehicles: None,
+            }
+        );
+
+        // Lenient mode coerces scalars
+        let value = json!([["foo", 1]]).to_arma();
+        assert_eq!(from_value::<crate::data::Test>(&value).unwrap().foo, "1");
+
+        let error = from_value_with_options::<crate::data::Test>(&value, &ParseOptions::strict())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid type: integer `1`, expected a string, in foo"
+        );
+
+        let value = json!([["foo", "bar"], ["extra", 1]]).to_arma();
+        let error = from_value_with_options::<crate::data::Test>(&value, &ParseOptions::strict())
+            .unwrap_err();
+        assert_eq!(error.message, "Unknown key \"extra\", expected one of: foo");
+    }
+
+    #[test]
+    fn it_only_reads_numbers_arma_writes_in_full_as_integers() {
+        assert_eq!(
+            from_value::<u64>(&ArmaValue::Number(999_999.0)).unwrap(),
+            999_999
+        );
+        assert_eq!(from_value::<i64>(&ArmaValue::Number(-5.0)).unwrap(), -5);
+        assert_eq!(
+            from_value::<String>(&ArmaValue::Number(999_999.0)).unwrap(),
+            "999999"
+        );
+
+        // Arma writes a million as 1e+006, so there is no telling if any of these were rounded
+        for number in [1e6, 3e6, -1.5e7, (2_u64.pow(60) + 1) as f64] {
+            let value = ArmaValue::Number(number);
+            assert_eq!(
+                from_value::<u64>(&value).unwrap_err().kind,
+                ParseErrorKind::LostPrecision,
+                "{number}"
+            );
+            assert_eq!(
+                from_value::<String>(&value).unwrap_err().kind,
+                ParseErrorKind::LostPrecision,
+                "{number}"
+            );
+            assert_eq!(
+                from_value::<crate::NumberString>(&value).unwrap_err().kind,
+                ParseErrorKind::LostPrecision,
+                "{number}"
+            );
+        }
+
+        assert_eq!(
+            from_value::<serde_json::Value>(&ArmaValue::Number(3e6)).unwrap(),
+            json!(3e6)
+        );
+
+        assert!(from_value::<u64>(&ArmaValue::Number(1.5)).is_err());
+    }
+
+    #[test]
+    fn it_reads_numbers_the_same_as_from_str() {
+        // The text Arma's str writes for each number, and the value arma-rs reads from that text
+        for (text, number) in [
+            ("1.23457e+009", 1.23457e9),
+            ("1e+006", 1e6),
+            ("-1.5e+007", -1.5e7),
+            ("999999", 999_999.0),
+            ("1500", 1500.0),
+            ("-0.25", -0.25),
+        ] {
+            let input = format!(r#"[["locker_poptabs",{text}],["respect","{text}"]]"#);
+            let value = json!([["locker_poptabs", number], ["respect", text]]).to_arma();
+            assert_eq!(
+                from_value::<Reward>(&value).map_err(|e| e.kind),
+                super::super::from_str::<Reward>(&input).map_err(|e| e.kind),
+                "{text}"
+            );
+
+            let input = format!("[{text}]");
+            let value = json!([number]).to_arma();
+            assert_eq!(
+                from_value::<Vec<i64>>(&value).map_err(|e| e.kind),
+                super::super::from_str::<Vec<i64>>(&input).map_err(|e| e.kind),
+                "{text}"
+            );
+            assert_eq!(
+                from_value::<serde_json::Value>(&value).unwrap(),
+                super::super::from_str::<serde_json::Value>(&input).unwrap(),
+                "{text}"
+            );
+        }
+
+        let value = json!([["type", "reward"], ["content", [["respect", 1.23457e9]]]]).to_arma();
+        let error = Data::from_arma_value(&value).unwrap_err();
+        assert!(error.contains("has lost precision"), "{error}");
+    }
+
+    #[test]
+    fn it_names_the_path_of_errors() {
+        let value = json!([["vehicles", [[["class_name", "a"]], [["class_name", [1]]]]]]).to_arma();
+        let error = from_value::<Reward>(&value).unwrap_err();
+        assert_eq!(error.path, "vehicles[1].class_name");
+        assert_eq!(error.kind, ParseErrorKind::Invalid);
+
+        let value = json!([[[[[1]]]]]).to_arma();
+        let options = ParseOptions {
+            max_depth: 3,
+            ..Default::default()
+        };
+
+        let error =
+            from_value_with_options::<Vec<Vec<Vec<Vec<Vec<u8>>>>>>(&value, &options).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::TooDeep);
+        assert_eq!(error.path, "[0][0][0]");
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use sha2::{Digest, Sha256};
+
+/*
+    Server ids and key fingerprints that are no longer allowed to send messages.
+
+    Revocation file format. One entry per line, blank lines and lines starting with # are ignored
+        server_id:esm_banned_community
+        fingerprint:<sha256 hex of the first 32 bytes of the server key>
+
+    The list is safe to share between threads. Call `RevocationList::reload_if_changed` periodically
+    (or `RevocationList::reload` when notified) to pick up changes without restarting.
+    Entries added with the revoke_* functions are kept separately, so a reload never drops them.
+
+    `RevocationList::install` makes a list the one `Message::from_bytes` checks before decrypting any frame, so
+    every consumer refuses revoked frames without changing how it decodes. `Message::from_bytes_checked` checks
+    another list on top of the installed one.
+*/
+// The list Message::from_bytes checks, if one was installed
+static INSTALLED: RwLock<Option<Arc<RevocationList>>> = RwLock::new(None);
+
+#[derive(Debug, Default)]
+pub struct RevocationList {
+    path: Option<PathBuf>,
+
+    // Read from the file and replaced on every reload
+    entries: RwLock<Entries>,
+
+    // Added with the revoke_* functions while running
+    revoked: RwLock<Entries>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Entries {
+    server_ids: HashSet<Vec<u8>>,
+    fingerprints: HashSet<String>,
+    modified_at: Option<SystemTime>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        RevocationList::default()
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let list = RevocationList {
+            path: Some(path.as_ref().to_path_buf()),
+            ..Default::default()
+        };
+
+        list.reload()?;
+        Ok(list)
+    }
+
+    /// Makes this the list every Message::from_bytes checks, replacing any list installed before it.
+    /// Reloads and revocations through the returned handle apply to the next frame decoded
+    pub fn install(self) -> Arc<RevocationList> {
+        let list = Arc::new(self);
+        *INSTALLED.write().unwrap() = Some(list.clone());
+        list
+    }
+
+    pub fn installed() -> Option<Arc<RevocationList>> {
+        INSTALLED.read().unwrap().clone()
+    }
+
+    /// Re-reads the file this list was loaded from. The current entries are kept if the file cannot be read.
+    /// Entries added with the revoke_* functions are kept either way
+    pub fn reload(&self) -> Result<(), String> {
+        let Some(path) = self.path.as_ref() else {
+            return Err("Revocation list was not loaded from a file".into());
+        };
+
+        let modified_at = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read revocation list at {path:?}. Reason: {e}"
+                ))
+            }
+        };
+
+        let mut entries = Entries::parse(&contents)?;
+        entries.modified_at = modified_at;
+
+        *self.entries.write().unwrap() = entries;
+        Ok(())
+    }
+
+    /// Reloads the file only if it has been modified since it was last read. Returns true if a reload happened
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(false);
+        };
+
+        let modified_at = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if modified_at.is_some() && modified_at == self.entries.read().unwrap().modified_at {
+            return Ok(false);
+        }
+
+        self.reload()?;
+        Ok(true)
+    }
+
+    pub fn revoke_server_id(&self, server_id: &[u8]) {
+        self.revoked
+            .write()
+            .unwrap()
+            .server_ids
+            .insert(server_id.to_vec());
+    }
+
+    pub fn revoke_key(&self, server_key: &[u8]) {
+        self.revoke_fingerprint(&key_fingerprint(server_key));
+    }
+
+    pub fn revoke_fingerprint(&self, fingerprint: &str) {
+        self.revoked
+            .write()
+            .unwrap()
+            .fingerprints
+            .insert(fingerprint.to_lowercase());
+    }
+
+    pub fn is_server_id_revoked(&self, server_id: &[u8]) -> bool {
+        [&self.entries, &self.revoked]
+            .iter()
+            .any(|entries| entries.read().unwrap().server_ids.contains(server_id))
+    }
+
+    pub fn is_key_revoked(&self, server_key: &[u8]) -> bool {
+        let fingerprint = key_fingerprint(server_key);
+
+        [&self.entries, &self.revoked]
+            .iter()
+            .any(|entries| entries.read().unwrap().fingerprints.contains(&fingerprint))
+    }
+
+    /// Checks the server id in the packet and the key that will be used to decrypt it
+    pub fn check(&self, bytes: &[u8], server_key: &[u8]) -> Result<(), DecodeError> {
+        if let Some(server_id) = read_server_id(bytes) {
+            if self.is_server_id_revoked(server_id) {
+                return Err(DecodeError::Revoked(format!(
+                    "Server id {:?} has been revoked",
+                    String::from_utf8_lossy(server_id)
+                )));
+            }
+        }
+
+        if self.is_key_revoked(server_key) {
+            return Err(DecodeError::Revoked(format!(
+                "Key {} has been revoked",
+                key_fingerprint(server_key)
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Entries {
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut entries = Entries::default();
+
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once(':') {
+                Some(("server_id", id)) => {
+                    entries.server_ids.insert(id.trim().as_bytes().to_vec());
+                }
+                Some(("fingerprint", fingerprint)) => {
+                    entries
+                        .fingerprints
+                        .insert(fingerprint.trim().to_lowercase());
+                }
+                _ => {
+                    return Err(format!(
+                        "Invalid revocation entry on line {}: {line:?}",
+                        line_number + 1
+                    ))
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// The SHA-256 hex digest of the part of the key used for encryption
+pub fn key_fingerprint(server_key: &[u8]) -> String {
+    let key = &server_key[0..server_key.len().min(32)];
+
+    Sha256::digest(key)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn read_server_id(bytes: &[u8]) -> Option<&[u8]> {
+    let id_length = *bytes.first()? as usize;
+    bytes.get(1..=id_length)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    // The server id or key is on the revocation list
+    Revoked(String),
+
+    // The message could not be decrypted or deserialized
+    Failed(String),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Revoked(e) => write!(f, "Revoked. {e}"),
+            DecodeError::Failed(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::random_server_key;
+    use crate::Message;
+    use uuid::Uuid;
+
+    #[test]
+    fn it_refuses_revoked_server_ids_and_keys() {
+        let server_key = random_server_key();
+        let bytes = Message::new()
+            .set_server_id(b"esm_testing")
+            .as_bytes(&server_key)
+            .unwrap();
+
+        let revocations = RevocationList::new();
+        assert!(Message::from_bytes_checked(&bytes, &server_key, &revocations).is_ok());
+
+        revocations.revoke_server_id(b"esm_testing");
+        assert!(matches!(
+            Message::from_bytes_checked(&bytes, &server_key, &revocations),
+            Err(DecodeError::Revoked(_))
+        ));
+
+        let revocations = RevocationList::new();
+        revocations.revoke_key(&server_key);
+        assert!(matches!(
+            Message::from_bytes_checked(&bytes, &server_key, &revocations),
+            Err(DecodeError::Revoked(_))
+        ));
+    }
+
+    #[test]
+    fn it_refuses_revoked_frames_in_from_bytes() {
+        // Unique to this test, as the installed list applies to every test running alongside it
+        let server_id = format!("esm_revoked_{}", Uuid::new_v4());
+        let server_key = random_server_key();
+        let bytes = Message::new()
+            .set_server_id(server_id.as_bytes())
+            .as_bytes(&server_key)
+            .unwrap();
+
+        let revocations = RevocationList::new().install();
+        assert!(Message::from_bytes(&bytes, &server_key).is_ok());
+
+        revocations.revoke_server_id(server_id.as_bytes());
+        let error = Message::from_bytes(&bytes, &server_key).unwrap_err();
+        assert_eq!(
+            error,
+            format!("Revoked. Server id {server_id:?} has been revoked")
+        );
+
+        // The installed list is checked by from_bytes_checked as well
+        assert!(matches!(
+            Message::from_bytes_checked(&bytes, &server_key, &RevocationList::new()),
+            Err(DecodeError::Revoked(_))
+        ));
+
+        let other_key = random_server_key();
+        let bytes = Message::new()
+            .set_server_id(b"esm_testing")
+            .as_bytes(&other_key)
+            .unwrap();
+
+        assert!(Message::from_bytes(&bytes, &other_key).is_ok());
+        RevocationList::installed().unwrap().revoke_key(&other_key);
+        assert!(Message::from_bytes(&bytes, &other_key)
+            .unwrap_err()
+            .starts_with("Revoked. Key "));
+    }
+
+    #[test]
+    fn it_loads_and_reloads_from_a_file() {
+        let server_key = random_server_key();
+        let path = std::env::temp_dir().join(format!("esm_revocations_{}", Uuid::new_v4()));
+
+        std::fs::write(&path, "# Banned\nserver_id:esm_banned\n\n").unwrap();
+
+        let revocations = RevocationList::from_file(&path).unwrap();
+        assert!(revocations.is_server_id_revoked(b"esm_banned"));
+        assert!(!revocations.is_key_revoked(&server_key));
+
+        std::fs::write(
+            &path,
+            format!(
+                "fingerprint:{}\n",
+                key_fingerprint(&server_key).to_uppercase()
+            ),
+        )
+        .unwrap();
+
+        revocations.reload().unwrap();
+        assert!(!revocations.is_server_id_revoked(b"esm_banned"));
+        assert!(revocations.is_key_revoked(&server_key));
+
+        std::fs::write(&path, "banned\n").unwrap();
+        assert!(revocations.reload().is_err());
+        assert!(revocations.is_key_revoked(&server_key));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn it_keeps_runtime_revocations_across_reloads() {
+        let server_key = random_server_key();
+        let path = std::env::temp_dir().join(format!("esm_revocations_{}", Uuid::new_v4()));
+
+        std::fs::write(&path, "server_id:esm_banned\n").unwrap();
+        let revocations = RevocationList::from_file(&path).unwrap();
+
+        revocations.revoke_server_id(b"esm_testing");
+        revocations.revoke_key(&server_key);
+
+        std::fs::write(&path, "# Nobody is banned anymore\n").unwrap();
+        revocations.reload().unwrap();
+
+        assert!(!revocations.is_server_id_revoked(b"esm_banned"));
+        assert!(revocations.is_server_id_revoked(b"esm_testing"));
+        assert!(revocations.is_key_revoked(&server_key));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Serialize, Serializer};
+
+/*
+    Writes a HashMap with its keys sorted. A HashMap iterates in a different order every run,
+    so without this the same data gives different Arma and JSON output each time.
+
+    Use it on any HashMap field with serde's serialize_with attribute:
+        #[serde(serialize_with = "esm_message::sorted::serialize")]
+
+    serialize_option and serialize_option_list cover Option<HashMap> and Option<Vec<HashMap>>.
+    Sorting does not need serde_json's preserve_order feature. That is only there so objects converted from
+    Arma keep their keys in the order SQF sent them
+*/
+
+pub fn serialize<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    sorted(map).serialize(serializer)
+}
+
+pub fn serialize_option<S, K, V>(
+    map: &Option<HashMap<K, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    map.as_ref().map(sorted).serialize(serializer)
+}
+
+pub fn serialize_option_list<S, K, V>(
+    maps: &Option<Vec<HashMap<K, V>>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Ord + Serialize,
+    V: Serialize,
+{
+    maps.as_ref()
+        .map(|maps| maps.iter().map(sorted).collect::<Vec<_>>())
+        .serialize(serializer)
+}
+
+fn sorted<K: Ord, V>(map: &HashMap<K, V>) -> BTreeMap<&K, &V> {
+    map.iter().collect()
+}
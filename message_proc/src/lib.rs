@@ -3,44 +3,33 @@ use quote::{format_ident, quote};
 use syn::DeriveInput;
 
 #[proc_macro_derive(ImplIntoArma)]
-/// Creates the to_arma function based on the attributes for this struct
+/// Creates the to_arma function based on the attributes for this struct.
+/// Also adds try_to_arma, which returns the serializer's error instead of panicking.
+/// The generated code refers to ::esm_message and ::arma_rs, so both need to be dependencies of the crate using it
 pub fn derive_into_arma(input: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(input as DeriveInput);
 
     let name = &input.ident;
     let struct_name = format_ident!("{}", &name);
 
-    let data = &input.data;
-    let mut arguments: Vec<syn::Ident> = Vec::new();
-
-    let data = match data {
-        syn::Data::Struct(data) => data,
-        _ => panic!("ImplIntoArma is only available for Structs"),
-    };
-
-    // Get all the struct fields
-    for field in &data.fields {
-        if let syn::Visibility::Restricted(_) = field.vis {
-            continue;
-        }
-        if field.ident.is_none() {
-            continue;
-        }
-
-        arguments.push(field.ident.as_ref().unwrap().to_owned());
+    if !matches!(&input.data, syn::Data::Struct(_)) {
+        panic!("ImplIntoArma is only available for Structs");
     }
 
-    // Builds the IntoArma implementation
+    // Builds the IntoArma implementation using the serde Serializer, so the output matches the struct's serde attributes
     let expanded = quote! {
-        impl arma_rs::IntoArma for #struct_name {
-            fn to_arma(&self) -> arma_rs::Value {
-                let mut vec: Vec<Vec<arma_rs::Value>> = Vec::new();
-
-                #(
-                    vec.push(vec![stringify!(#arguments).to_arma(), self.#arguments.to_arma()]);
-                )*
+        impl #struct_name {
+            pub fn try_to_arma(&self) -> Result<::arma_rs::Value, ::esm_message::parser::SerializeError> {
+                ::esm_message::parser::to_arma_value(self)
+            }
+        }
 
-                vec.to_arma()
+        impl ::arma_rs::IntoArma for #struct_name {
+            fn to_arma(&self) -> ::arma_rs::Value {
+                // The serializer only fails when a Serialize implementation returns an error
+                self.try_to_arma().unwrap_or_else(|e| {
+                    panic!("{} could not be written as an Arma value. {e}", stringify!(#struct_name))
+                })
             }
         }
     };